@@ -1,19 +1,258 @@
 use core::{
-    compare::{compare, print_diff},
-    parse::parse,
-    stringify::stringify,
+    binary::Encoding,
+    compare::{
+        ArrayKey, ColorChoice, CompareOptions, DiffReport, DiffStat, Granularity, ReportFormat,
+        compare_by, compare_values, normalize, terminal_width, write_diff, write_differences,
+        write_html, write_html_differences, write_inline_diff, write_markdown,
+        write_markdown_differences, write_side_by_side,
+    },
+    config::{Config, option_values},
+    error::Error,
+    file::{common_dir, expand_globs, mirrored_path, watch_files, write_atomic},
+    format::{Format, read_bytes, write_bytes},
+    infer::Inference,
+    jq::Filter,
+    merge::merge3,
+    parse::{parse, parse_tracked, reformat},
+    path::PathPattern,
+    query::{Query, QueryFormat, write_matches},
+    remove_spaces::remove_spaces_str,
+    stringify::{MissingPath, restringify, stringify_paths},
+    table::{Table, TableFormat, write_table},
+    validate::{Schema, ViolationFormat, write_violations},
 };
-use std::io::{Read, Write};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
 
-use clap::{Parser, Subcommand, command};
+use clap::{
+    ArgAction, Args as ClapArgs, CommandFactory, FromArgMatches, Parser, Subcommand, value_parser,
+};
 use clio::{Input, Output};
+use rayon::prelude::*;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Exit statuses shared by all subcommands, 1 is left to subcommands to report a result
+const EXIT_STATUS: &str = "Exit status:
+  0  success
+  1  inputs differ (compare), conflicts remain (merge3) or input breaks the schema (validate)
+  2  invalid arguments or other errors
+  3  input is not valid JSON, YAML, TOML, CSV, XML, MessagePack, CBOR or BSON
+  4  stringifying, prettifying or converting the output failed
+  5  reading or writing a file failed";
 
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, after_help = EXIT_STATUS)]
 /// JTool - a powerful JSON tool to parse and stringify nested JSON objects
 struct Args {
     #[command(subcommand)]
     command: Commands,
+    /// Ignore jtool.toml and the user config file
+    #[clap(long, global = true)]
+    no_config: bool,
+}
+
+/// Options for writing the result back over the input file
+#[derive(ClapArgs, Debug)]
+struct InPlace {
+    /// Write the result over the input file atomically, keeping its permissions
+    #[clap(long, short = 'i', conflicts_with = "output")]
+    in_place: bool,
+    /// Keep the original input as <input>.bak when editing in place
+    #[clap(long, requires = "in_place")]
+    backup: bool,
+}
+
+impl InPlace {
+    /// Write `out` over the `input` file or to `output`
+    fn write(&self, input: &Path, output: &mut Output, out: &[u8]) -> anyhow::Result<()> {
+        if self.in_place {
+            write_atomic(input, out, self.backup)?;
+            return Ok(());
+        }
+
+        Ok(output.write_all(out)?)
+    }
+}
+
+/// End `text` output with a newline, wherever it is written
+fn with_newline(mut out: Vec<u8>, text: bool) -> Vec<u8> {
+    if text && !out.ends_with(b"\n") {
+        out.push(b'\n');
+    }
+
+    out
+}
+
+/// Time to wait for changes to settle before re-running with `--watch`
+const WATCH_DELAY: Duration = Duration::from_millis(200);
+
+/// Options for processing many files at once or again on every change
+#[derive(ClapArgs, Debug)]
+struct Batch {
+    /// Process several files in parallel, given as paths or glob patterns, e.g. 'fixtures/**/*.json'
+    #[clap(long, num_args = 1.., conflicts_with_all = ["input", "output"])]
+    files: Vec<String>,
+    /// Write processed files under this directory, mirroring the tree they were found in
+    #[clap(long, requires = "files", conflicts_with = "in_place")]
+    out_dir: Option<PathBuf>,
+    /// Run again whenever an input file changes until interrupted, printing errors without exiting
+    #[clap(long, short)]
+    watch: bool,
+}
+
+impl Batch {
+    /// Run `transform` over `input`, or every file given with `--files`, and again on changes
+    /// with `--watch`, `text` output ending with a newline wherever it is written. `extension` is
+    /// given when the format changes, which rules out `--in-place`
+    fn run(
+        &self,
+        input: Input,
+        output: Output,
+        in_place: &InPlace,
+        extension: Option<&str>,
+        text: bool,
+        transform: impl Fn(&[u8]) -> anyhow::Result<Vec<u8>> + Sync,
+    ) -> anyhow::Result<ExitCode> {
+        // The file would keep its extension while holding another format
+        if in_place.in_place && extension.is_some() {
+            anyhow::bail!(
+                "--in-place cannot convert between formats, use --out-dir or an output file"
+            );
+        }
+        let transform =
+            |buf: &[u8]| -> anyhow::Result<Vec<u8>> { Ok(with_newline(transform(buf)?, text)) };
+        if !self.files.is_empty() {
+            return self.run_files(in_place, extension, transform);
+        }
+
+        // Checked before reading so STDIN is not consumed only to fail
+        if input.path().is_std() && (in_place.in_place || self.watch) {
+            anyhow::bail!("--in-place and --watch need an input file, not STDIN");
+        }
+
+        let mut input_handle = input;
+        let mut output_handle = output;
+        if !self.watch {
+            let mut buf = Vec::new();
+            input_handle.read_to_end(&mut buf)?;
+
+            let out = transform(&buf)?;
+            in_place.write(input_handle.path().path(), &mut output_handle, &out)?;
+
+            return Ok(ExitCode::SUCCESS);
+        }
+
+        let file = input_handle.path().path().to_path_buf();
+        // An output file is replaced atomically on every run so readers never see it half
+        // written, the handle opened for it is closed rather than written through
+        let target =
+            (!output_handle.path().is_std()).then(|| output_handle.path().path().to_path_buf());
+        let mut stdout = target.is_none().then_some(output_handle);
+        let mut process = || -> anyhow::Result<()> {
+            let buf = fs::read(&file)?;
+            let out = transform(&buf)?;
+
+            if in_place.in_place {
+                // Writing unchanged output would trigger another run
+                if out != buf {
+                    write_atomic(&file, &out, in_place.backup)?;
+                    eprintln!("Wrote {}", file.display());
+                }
+            } else if let Some(target) = &target {
+                write_atomic(target, &out, false)?;
+                eprintln!("Wrote {}", target.display());
+            } else if let Some(stdout) = &mut stdout {
+                in_place.write(&file, stdout, &out)?;
+            }
+            Ok(())
+        };
+
+        report(process());
+        eprintln!("Watching {}, press Ctrl-C to stop", file.display());
+        watch_files(std::slice::from_ref(&file), WATCH_DELAY, |_| {
+            report(process());
+            ControlFlow::Continue(())
+        })?;
+
+        Ok(ExitCode::SUCCESS)
+    }
+
+    /// Run `transform` over every file in parallel, writing each result in place or under
+    /// `--out-dir` with `extension` if the format changed, then print a summary to STDERR
+    fn run_files(
+        &self,
+        in_place: &InPlace,
+        extension: Option<&str>,
+        transform: impl Fn(&[u8]) -> anyhow::Result<Vec<u8>> + Sync,
+    ) -> anyhow::Result<ExitCode> {
+        if self.out_dir.is_none() && !in_place.in_place {
+            anyhow::bail!("--files needs --out-dir or --in-place to know where to write");
+        }
+        let files = expand_globs(&self.files)?;
+        let base = common_dir(&files);
+
+        let process = |file: &Path| -> anyhow::Result<()> {
+            let buf = fs::read(file)?;
+            let out = transform(&buf)?;
+            let Some(out_dir) = &self.out_dir else {
+                // Files left as they are keep their timestamps and are not re-run when watching
+                if out != buf {
+                    write_atomic(file, &out, in_place.backup)?;
+                }
+                return Ok(());
+            };
+
+            let mut target = mirrored_path(file, &base, out_dir);
+            if let Some(extension) = extension {
+                target.set_extension(extension);
+            }
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            Ok(fs::write(target, out)?)
+        };
+        let results: Vec<anyhow::Result<()>> = files.par_iter().map(|file| process(file)).collect();
+
+        let mut failures = Vec::new();
+        for (file, result) in files.iter().zip(results) {
+            if let Err(err) = result {
+                eprintln!("Failed {}: {}", file.display(), err);
+                failures.push(err);
+            }
+        }
+        eprintln!(
+            "{} succeeded, {} failed",
+            files.len() - failures.len(),
+            failures.len()
+        );
+
+        if self.watch {
+            eprintln!("Watching {} files, press Ctrl-C to stop", files.len());
+            watch_files(&files, WATCH_DELAY, |changed| {
+                for file in changed {
+                    match process(file) {
+                        Ok(()) => eprintln!("Processed {}", file.display()),
+                        Err(err) => eprintln!("Failed {}: {}", file.display(), err),
+                    }
+                }
+                ControlFlow::Continue(())
+            })?;
+        }
+
+        Ok(failures.first().map_or(ExitCode::SUCCESS, error_status))
+    }
+}
+
+/// Print the error of a run in watch mode, which keeps going
+fn report(result: anyhow::Result<()>) {
+    if let Err(err) = result {
+        eprintln!("Error: {:?}", err);
+    }
 }
 
 #[derive(Subcommand, Debug)]
@@ -27,9 +266,22 @@ enum Commands {
         /// Output file, defaults to STDOUT
         #[clap(value_parser, default_value = "-")]
         output: Output,
-        /// Prettify and format output JSON
+        /// Prettify and format output JSON or XML
         #[clap(long, short)]
         prettify: bool,
+        /// Input format: json, yaml, toml, csv, tsv, xml, msgpack, cbor or bson
+        #[clap(long, default_value = "json")]
+        from: Format,
+        /// Output format: json, yaml, toml, csv, tsv, xml, msgpack, cbor or bson
+        #[clap(long, default_value = "json")]
+        to: Format,
+        /// Encoding of binary input and output: raw, hex or base64, the latter two for pasted blobs
+        #[clap(long, short, default_value = "raw")]
+        encoding: Encoding,
+        #[command(flatten)]
+        in_place: InPlace,
+        #[command(flatten)]
+        batch: Batch,
     },
     /// Stringify JSON input and return the JSON string
     #[command(alias = "s")]
@@ -40,14 +292,152 @@ enum Commands {
         /// Output file, defaults to STDOUT
         #[clap(value_parser, default_value = "-")]
         output: Output,
-        /// List of key hierarchy sequences separated by (.) for nested stringification
+        /// List of key hierarchy sequences separated by (.) for nested stringification, @name expands a path set from the config
         #[clap(long, short)]
         paths: Option<Vec<String>>,
+        /// Named path set to stringify, built in are api-gateway, sns, sqs and kafka, more can be defined under [paths] in the config
+        #[clap(long)]
+        profile: Vec<String>,
+        /// What to do with paths that do not exist: error, skip or create-null
+        #[clap(long, default_value = "error")]
+        missing: MissingPath,
+        /// Input format: json, yaml, toml, csv, tsv, xml, msgpack, cbor or bson
+        #[clap(long, default_value = "json")]
+        from: Format,
+        /// Output format: json, yaml, toml, csv, tsv, xml, msgpack, cbor or bson, stringified values stay JSON strings inside it
+        #[clap(long, default_value = "json")]
+        to: Format,
+        /// Encoding of binary input and output: raw, hex or base64, the latter two for pasted blobs
+        #[clap(long, short, default_value = "raw")]
+        encoding: Encoding,
+        #[command(flatten)]
+        in_place: InPlace,
+        #[command(flatten)]
+        batch: Batch,
     },
-    /// Trim extra spaces and newlines from JSON
+    /// Trim extra spaces and newlines from JSON, unwrapping stringified JSON
     #[command(aliases = ["r", "rem"])]
-    RemoveSpaces {},
+    RemoveSpaces {
+        /// Input file, defaults to STDIN
+        #[clap(value_parser, default_value = "-")]
+        input: Input,
+        /// Output file, defaults to STDOUT
+        #[clap(value_parser, default_value = "-")]
+        output: Output,
+        #[command(flatten)]
+        in_place: InPlace,
+        #[command(flatten)]
+        batch: Batch,
+    },
+    /// Indent JSON as is, leaving stringified JSON alone
+    #[command(alias = "f")]
+    Format {
+        /// Input file, defaults to STDIN
+        #[clap(value_parser, default_value = "-")]
+        input: Input,
+        /// Output file, defaults to STDOUT
+        #[clap(value_parser, default_value = "-")]
+        output: Output,
+        /// Number of spaces to indent by
+        #[clap(long, default_value_t = 2)]
+        indent: usize,
+        #[command(flatten)]
+        in_place: InPlace,
+        #[command(flatten)]
+        batch: Batch,
+    },
+    /// Select values from unwrapped JSON input with an RFC 9535 JSONPath expression
+    #[command(alias = "q")]
+    Query {
+        /// JSONPath expression, e.g. '$.items[?@.price < 10].name'
+        #[clap(value_parser)]
+        expr: Query,
+        /// Input file, defaults to STDIN
+        #[clap(value_parser, default_value = "-")]
+        input: Input,
+        /// Output file, defaults to STDOUT
+        #[clap(value_parser, default_value = "-")]
+        output: Output,
+        /// Output format: json for one array of matches or lines for one value per line
+        #[clap(long, short, default_value = "json")]
+        format: QueryFormat,
+        /// Prettify and format the output JSON array
+        #[clap(long, short)]
+        prettify: bool,
+    },
+    /// Run a jq filter over unwrapped JSON input, printing every output on its own line
+    Jq {
+        /// jq filter, e.g. '.items | map(.name)'
+        #[clap(value_parser)]
+        filter: String,
+        /// Input file, defaults to STDIN
+        #[clap(value_parser, default_value = "-")]
+        input: Input,
+        /// Output file, defaults to STDOUT
+        #[clap(value_parser, default_value = "-")]
+        output: Output,
+        /// Prettify and format output JSON
+        #[clap(long, short)]
+        prettify: bool,
+        /// Print string outputs without quotes
+        #[clap(long, short)]
+        raw_output: bool,
+        /// Stringify values found at paths that were stringified in the input back again, for
+        /// filters keeping the input's structure such as `.` or `.a |= f`
+        #[clap(long)]
+        restringify: bool,
+    },
+    /// Export an array of objects as a table, flattening nested keys into dotted columns
+    #[command(alias = "t")]
+    Table {
+        /// Input file, defaults to STDIN
+        #[clap(value_parser, default_value = "-")]
+        input: Input,
+        /// Output file, defaults to STDOUT
+        #[clap(value_parser, default_value = "-")]
+        output: Output,
+        /// Output format: csv, tsv or markdown
+        #[clap(long, short, default_value = "csv")]
+        format: TableFormat,
+        /// Comma separated columns to export in order, e.g. id,user.name, defaults to all
+        #[clap(long, short, value_delimiter = ',')]
+        columns: Option<Vec<String>>,
+        /// Input format: json, yaml, toml, csv, tsv, xml, msgpack, cbor or bson
+        #[clap(long, default_value = "json")]
+        from: Format,
+        /// Encoding of binary input: raw, hex or base64
+        #[clap(long, short, default_value = "raw")]
+        encoding: Encoding,
+    },
+    /// Validate unwrapped input against a JSON Schema, draft 2020-12 unless the schema names another
+    ///
+    /// Exits with 0 when the input is valid, 1 when it is not and 2 or above on error
+    #[command(alias = "v")]
+    Validate {
+        /// JSON Schema file
+        #[clap(long, short)]
+        schema: Input,
+        /// Input file, defaults to STDIN
+        #[clap(value_parser, default_value = "-")]
+        input: Input,
+        /// Output file for the violations, defaults to STDOUT
+        #[clap(long, short, default_value = "-")]
+        output: Output,
+        /// Output format: text for one violation per line or json
+        #[clap(long, short, default_value = "text")]
+        format: ViolationFormat,
+        /// Input format: json, yaml, toml, csv, tsv, xml, msgpack, cbor or bson
+        #[clap(long, default_value = "json")]
+        from: Format,
+    },
+    /// Work with JSON Schemas
+    Schema {
+        #[command(subcommand)]
+        action: SchemaAction,
+    },
     /// Compare two JSON's and generate a diff
+    ///
+    /// Exits with 0 when the inputs are equal, 1 when they differ and 2 or above on error
     #[command(aliases = ["c", "diff", "d"])]
     Compare {
         /// Old file
@@ -56,17 +446,333 @@ enum Commands {
         /// New file
         #[clap(value_parser)]
         new: Input,
+        /// Key hierarchy patterns to leave out of the comparison, (*) and [*] match any key or index and (**) any depth, @name expands a path set from the config
+        #[clap(long, short)]
+        ignore: Vec<String>,
+        /// Match elements of arrays by an identity key instead of position, as <path>=<key> e.g. items=id
+        #[clap(long, short = 'k')]
+        array_key: Vec<ArrayKey>,
+        /// Match elements of arrays without an identity key by similarity instead of position
+        #[clap(long, short)]
+        match_similar: bool,
+        /// Treat numbers differing by at most this amount as equal
+        #[clap(long, default_value_t = 0.0)]
+        abs_tolerance: f64,
+        /// Treat numbers differing by at most this fraction of the larger one as equal
+        #[clap(long, default_value_t = 0.0)]
+        rel_tolerance: f64,
+        /// Treat integer and float representations of a number as equal, e.g. 1 and 1.0
+        #[clap(long)]
+        ignore_number_type: bool,
+        /// Treat strings holding a number as equal to that number, e.g. "1" and 1
+        #[clap(long)]
+        numeric_strings: bool,
+        /// List changed paths with their old and new values instead of a line diff
+        #[clap(long, short)]
+        structural: bool,
+        /// Show old and new in two columns fitted to the terminal width, a text line diff only
+        #[clap(long, short = 'y', conflicts_with = "structural")]
+        side_by_side: bool,
+        /// Print nothing, only report through the exit status
+        #[clap(long, short, conflicts_with = "stat")]
+        quiet: bool,
+        /// Print a summary of the number of changes instead of the diff
+        #[clap(long)]
+        stat: bool,
+        /// Unit to diff by: line, word or char, word and char suit minified single-line JSON
+        #[clap(long, short, default_value = "line")]
+        granularity: Granularity,
+        /// Output format: text, json for a machine-readable report, html or markdown to attach to tickets
+        #[clap(long, short, default_value = "text")]
+        format: ReportFormat,
+        /// When to color the diff: auto, always or never, auto honours NO_COLOR
+        #[clap(long, default_value = "auto")]
+        color: ColorChoice,
+    },
+    /// Three-way merge of two JSON's derived from a common base
+    ///
+    /// Exits with 0 when merged cleanly, 1 when there are conflicts and 2 or above on error
+    Merge3 {
+        /// Common ancestor file
+        #[clap(value_parser)]
+        base: Input,
+        /// Our changed file, whose value is kept on conflicts
+        #[clap(value_parser)]
+        ours: Input,
+        /// Their changed file
+        #[clap(value_parser)]
+        theirs: Input,
+        /// Output file, defaults to STDOUT
+        #[clap(value_parser, default_value = "-")]
+        output: Output,
+        /// Prettify and format output JSON
+        #[clap(long, short)]
+        prettify: bool,
+    },
+    /// Inspect configuration read from jtool.toml and the user config file
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigAction {
+    /// Print the effective configuration and the files it was read from
+    Show,
+}
+
+#[derive(Subcommand, Debug)]
+enum SchemaAction {
+    /// Infer a draft 2020-12 schema from unwrapped sample documents, marking stringified fields with x-stringified
+    Infer {
+        /// Sample files, defaults to STDIN
+        #[clap(value_parser, default_value = "-")]
+        inputs: Vec<Input>,
+        /// Output file, defaults to STDOUT
+        #[clap(long, short, default_value = "-")]
+        output: Output,
+        /// Read every line of the samples as its own document, as in JSON Lines
+        #[clap(long, short)]
+        lines: bool,
     },
 }
 
-fn main() -> anyhow::Result<()> {
-    let args = Args::parse();
+/// Exit status of a compare, following diff(1): 0 when equal and 1 when different
+fn compare_status(stat: &DiffStat) -> ExitCode {
+    if stat.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(1)
+    }
+}
+
+/// Exit status of a failed run, as listed in [`EXIT_STATUS`]
+fn error_status(err: &anyhow::Error) -> ExitCode {
+    let code = match err.downcast_ref::<Error>() {
+        Some(Error::JSONParsing { .. } | Error::FormatRead { .. }) => 3,
+        Some(
+            Error::JSONStringify { .. }
+            | Error::MissingPaths(_)
+            | Error::FormatWrite { .. }
+            | Error::Prettify(_),
+        ) => 4,
+        // Reading text that is not UTF-8 fails with InvalidData
+        Some(Error::Io(err)) if err.kind() == io::ErrorKind::InvalidData => 3,
+        Some(Error::Io(_) | Error::Watch(_)) => 5,
+        Some(_) => 2,
+        None => match err.downcast_ref::<io::Error>() {
+            Some(err) if err.kind() == io::ErrorKind::InvalidData => 3,
+            Some(_) => 5,
+            None => 2,
+        },
+    };
+
+    ExitCode::from(code)
+}
+
+fn write_json<W: Write, T: Serialize>(out: &mut W, val: &T) -> io::Result<()> {
+    serde_json::to_writer_pretty(&mut *out, val)?;
+    writeln!(out)
+}
+
+/// JSON input as text, invalid UTF-8 being a read error like any other malformed input
+fn json_text(buf: &[u8]) -> Result<String, Error> {
+    String::from_utf8(buf.to_vec()).map_err(|err| Error::FormatRead {
+        format: Format::Json,
+        reason: err.to_string(),
+    })
+}
+
+fn is_raw_binary(format: Format, encoding: Encoding) -> bool {
+    format.is_binary() && encoding == Encoding::Raw
+}
+
+/// `--encoding` applies to whichever side is binary, text formats are always raw
+fn encodings(from: Format, to: Format, encoding: Encoding) -> anyhow::Result<(Encoding, Encoding)> {
+    if encoding != Encoding::Raw && !from.is_binary() && !to.is_binary() {
+        anyhow::bail!("--encoding only applies to msgpack, cbor or bson input or output");
+    }
+    let side = |format: Format| match format.is_binary() {
+        true => encoding,
+        false => Encoding::Raw,
+    };
+
+    Ok((side(from), side(to)))
+}
+
+/// A reader closing the pipe early, e.g. `| head`, is not a failure
+fn ignore_broken_pipe(result: io::Result<()>) -> io::Result<()> {
+    match result {
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => result,
+    }
+}
+
+/// Command line with option defaults taken from `config`, so options given explicitly win.
+///
+/// Unknown subcommands and options are skipped with a warning, so a config written for another
+/// version does not break every invocation. Flags given a default take an optional value, e.g.
+/// `--prettify=false`, so the default can be turned off.
+fn command_with_config(config: &Config) -> clap::Command {
+    let mut command = Args::command();
+    for (name, options) in &config.commands {
+        let Some(subcommand) = command.find_subcommand(name) else {
+            eprintln!("Warning: unknown subcommand [{}] in config, ignored", name);
+            continue;
+        };
+
+        let mut defaults = Vec::new();
+        for (option, val) in options {
+            let Some(arg) = subcommand
+                .get_arguments()
+                .find(|arg| arg.get_long() == Some(option.as_str()))
+            else {
+                eprintln!(
+                    "Warning: unknown option {} for [{}] in config, ignored",
+                    option, name
+                );
+                continue;
+            };
+            defaults.push((arg.get_id().to_string(), option_values(val)));
+        }
+
+        let name = subcommand.get_name().to_string();
+        command = command.mut_subcommand(name, |subcommand| {
+            defaults
+                .into_iter()
+                .fold(subcommand, |subcommand, (id, values)| {
+                    subcommand.mut_arg(id, |arg| with_default(arg, values))
+                })
+        });
+    }
+
+    command
+}
+
+/// `arg` defaulting to `values`, a flag becoming `--flag[=true|false]` so it can be negated
+fn with_default(arg: clap::Arg, values: Vec<String>) -> clap::Arg {
+    let arg = match arg.get_action() {
+        ArgAction::SetTrue => arg
+            .action(ArgAction::Set)
+            .value_parser(value_parser!(bool))
+            .num_args(0..=1)
+            .require_equals(true)
+            .default_missing_value("true"),
+        _ => arg,
+    };
 
+    arg.default_values(values)
+}
+
+/// Parse the command line over configured defaults, unless `--no-config` is given
+fn parse_args() -> anyhow::Result<(Args, Config, Vec<PathBuf>)> {
+    if std::env::args_os().any(|arg| arg == "--no-config") {
+        return Ok((Args::parse(), Config::builtin(), Vec::new()));
+    }
+
+    let (config, sources) = Config::discover(&std::env::current_dir()?)?;
+    let matches = command_with_config(&config).get_matches();
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+
+    Ok((args, config, sources))
+}
+
+fn main() -> ExitCode {
+    let result = parse_args().and_then(|(args, config, sources)| run(args, &config, &sources));
+
+    match result {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("Error: {:?}", err);
+            error_status(&err)
+        }
+    }
+}
+
+fn run(args: Args, config: &Config, sources: &[PathBuf]) -> anyhow::Result<ExitCode> {
     match args.command {
         Commands::Parse {
             input,
             output,
             prettify,
+            from,
+            to,
+            encoding,
+            in_place,
+            batch,
+        } => {
+            let (from_encoding, to_encoding) = encodings(from, to, encoding)?;
+            let transform = |buf: &[u8]| -> anyhow::Result<Vec<u8>> {
+                let json = read_bytes(buf, from, from_encoding)?;
+                Ok(write_bytes(&json, to, prettify, to_encoding)?)
+            };
+            // Raw binary is left without a trailing newline
+            let text = !is_raw_binary(to, to_encoding);
+            let extension = (from != to).then(|| to.extension());
+            batch.run(input, output, &in_place, extension, text, transform)
+        }
+        Commands::Stringify {
+            input,
+            output,
+            paths,
+            profile,
+            missing,
+            from,
+            to,
+            encoding,
+            in_place,
+            batch,
+        } => {
+            let paths: Vec<String> = profile
+                .iter()
+                .map(|name| format!("@{}", name))
+                .chain(paths.unwrap_or_default())
+                .collect();
+            let paths = config.expand_paths(&paths)?;
+            let str_paths: Option<Vec<&str>> = Some(paths.iter().map(|s| s.as_ref()).collect());
+            let (from_encoding, to_encoding) = encodings(from, to, encoding)?;
+            let transform = |buf: &[u8]| -> anyhow::Result<Vec<u8>> {
+                let val = read_bytes(buf, from, from_encoding)?;
+                let val = stringify_paths(val, str_paths.clone(), missing)?;
+                Ok(write_bytes(&val, to, false, to_encoding)?)
+            };
+            // Raw binary is left without a trailing newline
+            let text = !is_raw_binary(to, to_encoding);
+            let extension = (from != to).then(|| to.extension());
+            batch.run(input, output, &in_place, extension, text, transform)
+        }
+        Commands::RemoveSpaces {
+            input,
+            output,
+            in_place,
+            batch,
+        } => {
+            let transform = |buf: &[u8]| -> anyhow::Result<Vec<u8>> {
+                let buf = json_text(buf)?;
+                Ok(remove_spaces_str(buf)?.into_bytes())
+            };
+            batch.run(input, output, &in_place, None, true, transform)
+        }
+        Commands::Format {
+            input,
+            output,
+            indent,
+            in_place,
+            batch,
+        } => {
+            let transform = |buf: &[u8]| -> anyhow::Result<Vec<u8>> {
+                let buf = json_text(buf)?;
+                Ok(reformat(&buf, Some(indent))?.into_bytes())
+            };
+            batch.run(input, output, &in_place, None, true, transform)
+        }
+        Commands::Query {
+            expr,
+            input,
+            output,
+            format,
+            prettify,
         } => {
             let mut buf = String::new();
             let mut input_handle = input;
@@ -74,49 +780,157 @@ fn main() -> anyhow::Result<()> {
             let mut output_handle = output;
 
             let json = parse(buf)?;
+            let matches = expr.select(&json);
 
-            let json_str = if prettify {
-                core::parse::prettify(json)?
-            } else {
-                json.to_string()
-            };
+            ignore_broken_pipe(write_matches(
+                &mut output_handle,
+                &matches,
+                format,
+                prettify,
+            ))?;
 
-            write!(output_handle, "{}", json_str)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::Jq {
+            filter: filter_text,
+            input,
+            output,
+            prettify,
+            raw_output,
+            restringify: restringify_paths,
+        } => {
+            let filter: Filter = filter_text.parse()?;
+            if restringify_paths && !filter.keeps_structure() {
+                return Err(Error::InvalidFilter(format!(
+                    "--restringify needs a filter keeping the input's structure, such as . or \
+                     an update assignment - {}",
+                    filter_text
+                ))
+                .into());
+            }
 
-            // Add a newline if output to stdout
-            if output_handle.path().is_std() {
-                println!("")
+            let mut buf = String::new();
+            let mut input_handle = input;
+            input_handle.read_to_string(&mut buf)?;
+            let mut output_handle = output;
+
+            let (json, decoded) = parse_tracked(buf)?;
+
+            let mut lines = Vec::new();
+            for mut val in filter.run(json)? {
+                if restringify_paths {
+                    restringify(&mut val, &decoded);
+                }
+                lines.push(match val {
+                    Value::String(str) if raw_output => str,
+                    val if prettify => core::parse::prettify(val)?,
+                    val => val.to_string(),
+                });
             }
+            let written = lines
+                .iter()
+                .try_for_each(|line| writeln!(output_handle, "{}", line));
+            ignore_broken_pipe(written)?;
 
-            Ok(())
+            Ok(ExitCode::SUCCESS)
         }
-        Commands::Stringify {
+        Commands::Table {
             input,
             output,
-            paths,
+            format,
+            columns,
+            from,
+            encoding,
         } => {
-            let mut buf = String::new();
+            let mut buf = Vec::new();
             let mut input_handle = input;
-            input_handle.read_to_string(&mut buf)?;
+            input_handle.read_to_end(&mut buf)?;
             let mut output_handle = output;
 
-            let str_paths: Option<Vec<&str>> = paths
-                .as_ref()
-                .map(|vec| vec.iter().map(|s| s.as_ref()).collect());
-            let val = parse(buf)?;
-            let str = stringify(val, str_paths)?;
+            let (from_encoding, _) = encodings(from, from, encoding)?;
+            let json = read_bytes(&buf, from, from_encoding)?;
+            let table = Table::from_value(&json, columns.as_deref());
+            ignore_broken_pipe(write_table(&mut output_handle, &table, format))?;
+
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::Validate {
+            schema,
+            input,
+            output,
+            format,
+            from,
+        } => {
+            let mut schema_buf = String::new();
+            let mut schema_handle = schema;
+            schema_handle.read_to_string(&mut schema_buf)?;
+            let schema: Schema = schema_buf.parse()?;
+
+            let mut buf = Vec::new();
+            let mut input_handle = input;
+            input_handle.read_to_end(&mut buf)?;
 
-            write!(output_handle, "{}", str)?;
+            let mut output_handle = output;
 
-            // Add a newline if output to stdout
-            if output_handle.path().is_std() {
-                println!("")
+            let violations = schema.validate(&read_bytes(&buf, from, Encoding::Raw)?);
+            ignore_broken_pipe(write_violations(&mut output_handle, &violations, format))?;
+
+            match violations.is_empty() {
+                true => Ok(ExitCode::SUCCESS),
+                false => Ok(ExitCode::from(1)),
             }
+        }
+        Commands::Schema {
+            action:
+                SchemaAction::Infer {
+                    inputs,
+                    output,
+                    lines,
+                },
+        } => {
+            let mut inference = Inference::new();
+            for input in inputs {
+                let mut buf = String::new();
+                let mut input_handle = input;
+                input_handle.read_to_string(&mut buf)?;
 
-            Ok(())
+                match lines {
+                    true => inference.add_lines(&buf)?,
+                    false => inference.add(buf)?,
+                }
+            }
+
+            let mut output_handle = output;
+            writeln!(
+                output_handle,
+                "{}",
+                core::parse::prettify(inference.schema())?
+            )?;
+
+            Ok(ExitCode::SUCCESS)
         }
-        Commands::RemoveSpaces {} => Ok(()),
-        Commands::Compare { old, new } => {
+        Commands::Compare {
+            old,
+            new,
+            ignore,
+            array_key,
+            match_similar,
+            abs_tolerance,
+            rel_tolerance,
+            ignore_number_type,
+            numeric_strings,
+            structural,
+            side_by_side,
+            quiet,
+            stat,
+            granularity,
+            format,
+            color,
+        } => {
+            if side_by_side && (format != ReportFormat::Text || granularity != Granularity::Line) {
+                anyhow::bail!("--side-by-side only supports text format and line granularity");
+            }
+
             let mut old_buf = String::new();
             let mut new_buf = String::new();
             let mut old_handle = old;
@@ -125,10 +939,141 @@ fn main() -> anyhow::Result<()> {
             old_handle.read_to_string(&mut old_buf)?;
             new_handle.read_to_string(&mut new_buf)?;
 
-            let diff = compare(old_buf.as_str(), new_buf.as_str())?;
-            print_diff(&diff);
+            let ignore = config
+                .expand_paths(&ignore)?
+                .iter()
+                .map(|pattern| pattern.parse())
+                .collect::<Result<Vec<PathPattern>, Error>>()?;
+            let options = CompareOptions {
+                ignore,
+                array_keys: array_key,
+                match_similar,
+                abs_tolerance,
+                rel_tolerance,
+                ignore_number_type,
+                numeric_strings,
+            };
+
+            if structural {
+                let differences = compare_values(&parse(old_buf)?, &parse(new_buf)?, &options);
+                let diff_stat = DiffStat::from_differences(&differences);
 
-            Ok(())
+                let mut stdout = io::stdout().lock();
+                let written = if stat {
+                    writeln!(stdout, "{}", diff_stat)
+                } else if quiet {
+                    Ok(())
+                } else {
+                    match format {
+                        ReportFormat::Text => {
+                            write_differences(&mut stdout, &differences, color.enabled())
+                        }
+                        ReportFormat::Json => {
+                            write_json(&mut stdout, &DiffReport::from_differences(&differences))
+                        }
+                        ReportFormat::Html => write_html_differences(&mut stdout, &differences),
+                        ReportFormat::Markdown => {
+                            write_markdown_differences(&mut stdout, &differences)
+                        }
+                    }
+                };
+                ignore_broken_pipe(written)?;
+
+                return Ok(compare_status(&diff_stat));
+            }
+
+            // Go through JSON only when there is something to filter, so raw formatting diffs still show
+            if options != CompareOptions::default() {
+                let mut old_json = parse(old_buf)?;
+                let mut new_json = parse(new_buf)?;
+                normalize(&mut old_json, &mut new_json, &options);
+
+                old_buf = core::parse::prettify(old_json)? + "\n";
+                new_buf = core::parse::prettify(new_json)? + "\n";
+            }
+
+            let diff = compare_by(old_buf.as_str(), new_buf.as_str(), granularity)?;
+            let diff_stat = DiffStat::from_text(&diff);
+
+            if granularity != Granularity::Line
+                && matches!(format, ReportFormat::Html | ReportFormat::Markdown)
+            {
+                anyhow::bail!("HTML and Markdown reports only support line granularity");
+            }
+
+            let mut stdout = io::stdout().lock();
+            let written = if stat {
+                writeln!(stdout, "{}", diff_stat)
+            } else if quiet {
+                Ok(())
+            } else {
+                match format {
+                    ReportFormat::Json => write_json(&mut stdout, &DiffReport::from_text(&diff)),
+                    ReportFormat::Html => write_html(&mut stdout, &diff),
+                    ReportFormat::Markdown => write_markdown(&mut stdout, &diff),
+                    ReportFormat::Text if granularity != Granularity::Line => {
+                        write_inline_diff(&mut stdout, &diff, color.enabled())
+                    }
+                    ReportFormat::Text if side_by_side => {
+                        write_side_by_side(&mut stdout, &diff, terminal_width(), color.enabled())
+                    }
+                    ReportFormat::Text => write_diff(&mut stdout, &diff, color.enabled()),
+                }
+            };
+            ignore_broken_pipe(written)?;
+
+            Ok(compare_status(&diff_stat))
+        }
+        Commands::Merge3 {
+            base,
+            ours,
+            theirs,
+            output,
+            prettify,
+        } => {
+            let mut bufs = [String::new(), String::new(), String::new()];
+            for (handle, buf) in [base, ours, theirs].iter_mut().zip(bufs.iter_mut()) {
+                handle.read_to_string(buf)?;
+            }
+            let [base_buf, ours_buf, theirs_buf] = bufs;
+            let mut output_handle = output;
+
+            let result = merge3(&parse(base_buf)?, &parse(ours_buf)?, &parse(theirs_buf)?);
+
+            let json_str = if prettify {
+                core::parse::prettify(result.merged)?
+            } else {
+                result.merged.to_string()
+            };
+
+            write!(output_handle, "{}", json_str)?;
+
+            // Add a newline if output to stdout
+            if output_handle.path().is_std() {
+                println!()
+            }
+
+            for conflict in &result.conflicts {
+                eprintln!("Conflict at {}", conflict);
+            }
+
+            match result.conflicts.is_empty() {
+                true => Ok(ExitCode::SUCCESS),
+                false => Ok(ExitCode::from(1)),
+            }
+        }
+        Commands::Config {
+            action: ConfigAction::Show,
+        } => {
+            if sources.is_empty() {
+                println!("# No configuration files found");
+            }
+            for source in sources {
+                println!("# {}", source.display());
+            }
+            print!("{}", config.to_toml()?);
+
+            Ok(ExitCode::SUCCESS)
         }
     }
 }
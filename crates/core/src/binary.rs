@@ -0,0 +1,380 @@
+//! MessagePack, CBOR and BSON decoding and encoding.
+//!
+//! Values JSON cannot hold are mapped on the way in: byte strings, and MessagePack strings that
+//! are not valid UTF-8 as written by older encoders, become base64 strings, MessagePack
+//! extensions become `{"type": n, "data": "<base64>"}`, CBOR tags are dropped in favour of the
+//! tagged value, non-string map keys become their JSON text and BSON types use relaxed Extended
+//! JSON, e.g. `{"$oid": "..."}`. Other CBOR items are an error rather than being lost. Several
+//! concatenated values, as found in streams and dumps, are read as an array.
+
+use std::str::FromStr;
+
+use base64::Engine;
+use base64::engine::DecodePaddingMode;
+use base64::engine::general_purpose::{GeneralPurpose, GeneralPurposeConfig, STANDARD};
+use serde_json::{Map, Number, Value};
+
+use crate::error::Error;
+use crate::format::Format;
+use crate::path::JsonPath;
+
+/// Accept base64 with or without padding, as blobs are often pasted with it trimmed
+const BASE64_LENIENT: GeneralPurpose = GeneralPurpose::new(
+    &base64::alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// How binary input and output is represented, hex and base64 suiting pasted blobs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    #[default]
+    Raw,
+    Hex,
+    Base64,
+}
+
+impl FromStr for Encoding {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "raw" => Ok(Encoding::Raw),
+            "hex" => Ok(Encoding::Hex),
+            "base64" => Ok(Encoding::Base64),
+            _ => Err(Error::InvalidOption(format!(
+                "Expected raw, hex or base64 for encoding - {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// Bytes of a blob given in `encoding`, ignoring whitespace around and inside hex and base64
+pub fn decode_blob(input: &[u8], encoding: Encoding, format: Format) -> Result<Vec<u8>, Error> {
+    let text: Vec<u8> = input
+        .iter()
+        .copied()
+        .filter(|byte| !byte.is_ascii_whitespace())
+        .collect();
+    let read_error = |reason: String| Error::FormatRead { format, reason };
+
+    match encoding {
+        Encoding::Raw => Ok(input.to_vec()),
+        Encoding::Hex => {
+            hex::decode(text).map_err(|err| read_error(format!("Invalid hex - {}", err)))
+        }
+        Encoding::Base64 => BASE64_LENIENT
+            .decode(text)
+            .map_err(|err| read_error(format!("Invalid base64 - {}", err))),
+    }
+}
+
+pub fn encode_blob(bytes: Vec<u8>, encoding: Encoding) -> Vec<u8> {
+    match encoding {
+        Encoding::Raw => bytes,
+        Encoding::Hex => (hex::encode(bytes) + "\n").into_bytes(),
+        Encoding::Base64 => (STANDARD.encode(bytes) + "\n").into_bytes(),
+    }
+}
+
+/// Every value in `input`, as one value or an array when there are several
+fn read_all<T>(
+    mut input: &[u8],
+    mut read_one: impl FnMut(&mut &[u8]) -> Result<T, String>,
+    to_json: impl Fn(T) -> Result<Value, String>,
+    format: Format,
+) -> Result<Value, Error> {
+    let read_error = |reason: String| Error::FormatRead { format, reason };
+
+    let mut values = Vec::new();
+    while !input.is_empty() {
+        let val = read_one(&mut input).map_err(read_error)?;
+        values.push(to_json(val).map_err(read_error)?);
+    }
+
+    match values.len() {
+        0 => Err(Error::FormatRead {
+            format,
+            reason: String::from("Empty input"),
+        }),
+        1 => Ok(values.remove(0)),
+        _ => Ok(Value::Array(values)),
+    }
+}
+
+fn float(val: f64) -> Value {
+    Number::from_f64(val).map_or(Value::Null, Value::Number)
+}
+
+/// Map keys that are not strings are keyed by their JSON text
+fn key(val: Value) -> String {
+    match val {
+        Value::String(str) => str,
+        val => val.to_string(),
+    }
+}
+
+fn msgpack_to_json(val: rmpv::Value) -> Value {
+    match val {
+        rmpv::Value::Nil => Value::Null,
+        rmpv::Value::Boolean(bool) => Value::Bool(bool),
+        rmpv::Value::Integer(int) => match (int.as_u64(), int.as_i64()) {
+            (Some(uint), _) => Value::from(uint),
+            (_, Some(int)) => Value::from(int),
+            _ => Value::Null,
+        },
+        rmpv::Value::F32(val) => float(val as f64),
+        rmpv::Value::F64(val) => float(val),
+        rmpv::Value::String(str) if str.is_str() => {
+            Value::String(str.into_str().unwrap_or_default())
+        }
+        rmpv::Value::String(str) => Value::String(STANDARD.encode(str.into_bytes())),
+        rmpv::Value::Binary(bytes) => Value::String(STANDARD.encode(bytes)),
+        rmpv::Value::Array(arr) => Value::Array(arr.into_iter().map(msgpack_to_json).collect()),
+        rmpv::Value::Map(entries) => Value::Object(Map::from_iter(
+            entries
+                .into_iter()
+                .map(|(k, v)| (key(msgpack_to_json(k)), msgpack_to_json(v))),
+        )),
+        rmpv::Value::Ext(kind, data) => {
+            let mut ext = Map::new();
+            ext.insert(String::from("type"), Value::from(kind));
+            ext.insert(String::from("data"), Value::String(STANDARD.encode(data)));
+            Value::Object(ext)
+        }
+    }
+}
+
+fn json_to_msgpack(val: &Value) -> rmpv::Value {
+    match val {
+        Value::Null => rmpv::Value::Nil,
+        Value::Bool(bool) => rmpv::Value::Boolean(*bool),
+        Value::Number(num) => match (num.as_u64(), num.as_i64()) {
+            (Some(uint), _) => rmpv::Value::from(uint),
+            (_, Some(int)) => rmpv::Value::from(int),
+            _ => rmpv::Value::F64(num.as_f64().unwrap_or_default()),
+        },
+        Value::String(str) => rmpv::Value::from(str.as_str()),
+        Value::Array(arr) => rmpv::Value::Array(arr.iter().map(json_to_msgpack).collect()),
+        Value::Object(obj) => rmpv::Value::Map(
+            obj.iter()
+                .map(|(k, v)| (rmpv::Value::from(k.as_str()), json_to_msgpack(v)))
+                .collect(),
+        ),
+    }
+}
+
+fn cbor_to_json(val: ciborium::Value) -> Result<Value, String> {
+    let val = match val {
+        ciborium::Value::Null => Value::Null,
+        ciborium::Value::Bool(bool) => Value::Bool(bool),
+        ciborium::Value::Integer(int) => {
+            let int = i128::from(int);
+            match (u64::try_from(int), i64::try_from(int)) {
+                (Ok(uint), _) => Value::from(uint),
+                (_, Ok(int)) => Value::from(int),
+                _ => Value::String(int.to_string()),
+            }
+        }
+        ciborium::Value::Float(val) => float(val),
+        ciborium::Value::Text(str) => Value::String(str),
+        ciborium::Value::Bytes(bytes) => Value::String(STANDARD.encode(bytes)),
+        ciborium::Value::Array(arr) => Value::Array(
+            arr.into_iter()
+                .map(cbor_to_json)
+                .collect::<Result<_, _>>()?,
+        ),
+        ciborium::Value::Map(entries) => Value::Object(
+            entries
+                .into_iter()
+                .map(|(k, v)| Ok((key(cbor_to_json(k)?), cbor_to_json(v)?)))
+                .collect::<Result<_, String>>()?,
+        ),
+        ciborium::Value::Tag(_, val) => cbor_to_json(*val)?,
+        val => return Err(format!("Unsupported CBOR item - {:?}", val)),
+    };
+
+    Ok(val)
+}
+
+pub fn read_msgpack(input: &[u8]) -> Result<Value, Error> {
+    read_all(
+        input,
+        |input| rmpv::decode::read_value(input).map_err(|err| err.to_string()),
+        |val| Ok(msgpack_to_json(val)),
+        Format::Msgpack,
+    )
+}
+
+pub fn read_cbor(input: &[u8]) -> Result<Value, Error> {
+    read_all(
+        input,
+        |input| ciborium::de::from_reader(input).map_err(|err| err.to_string()),
+        cbor_to_json,
+        Format::Cbor,
+    )
+}
+
+pub fn read_bson(input: &[u8]) -> Result<Value, Error> {
+    read_all(
+        input,
+        |input| bson::Document::from_reader(input).map_err(|err| err.to_string()),
+        |doc| Ok(bson::Bson::Document(doc).into_relaxed_extjson()),
+        Format::Bson,
+    )
+}
+
+pub fn write_msgpack(val: &Value) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::new();
+    rmpv::encode::write_value(&mut out, &json_to_msgpack(val)).map_err(|err| {
+        Error::FormatWrite {
+            format: Format::Msgpack,
+            path: JsonPath::default(),
+            reason: err.to_string(),
+        }
+    })?;
+
+    Ok(out)
+}
+
+pub fn write_cbor(val: &Value) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::new();
+    ciborium::into_writer(val, &mut out).map_err(|err| Error::FormatWrite {
+        format: Format::Cbor,
+        path: JsonPath::default(),
+        reason: err.to_string(),
+    })?;
+
+    Ok(out)
+}
+
+/// BSON holds documents only, so the value must be an object or an array of objects written
+/// one after another
+pub fn write_bson(val: &Value) -> Result<Vec<u8>, Error> {
+    let root = JsonPath::default();
+    let (docs, paths): (Vec<&Value>, Vec<JsonPath>) = match val {
+        Value::Array(arr) => arr
+            .iter()
+            .enumerate()
+            .map(|(idx, doc)| (doc, root.index(idx)))
+            .unzip(),
+        doc => (vec![doc], vec![root]),
+    };
+
+    let mut out = Vec::new();
+    for (doc, path) in docs.into_iter().zip(paths) {
+        let write_error = |reason: String| Error::FormatWrite {
+            format: Format::Bson,
+            path: path.clone(),
+            reason,
+        };
+        let doc = match bson::Bson::try_from(doc.clone()) {
+            Ok(bson::Bson::Document(doc)) => doc,
+            Ok(_) => {
+                return Err(write_error(String::from(
+                    "BSON holds documents, expected an object",
+                )));
+            }
+            Err(err) => return Err(write_error(err.to_string())),
+        };
+        doc.to_writer(&mut out)
+            .map_err(|err| write_error(err.to_string()))?;
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_msgpack_round_trip() {
+        let val = json!({"a": [1, -2, 1.5, "x", null, true], "b": {"c": 18446744073709551615u64}});
+
+        let actual = read_msgpack(&write_msgpack(&val).unwrap()).unwrap();
+
+        assert_eq!(val, actual);
+    }
+
+    #[test]
+    fn test_read_msgpack_binary_and_int_keys() {
+        // {1: bin8 [0x01, 0x02]}
+        let input = [0x81, 0x01, 0xc4, 0x02, 0x01, 0x02];
+
+        let actual = read_msgpack(&input).unwrap();
+
+        assert_eq!(json!({"1": "AQI="}), actual);
+    }
+
+    #[test]
+    fn test_read_msgpack_invalid_utf8_string_as_base64() {
+        // str8 [0xff, 0xfe], as older encoders wrote raw bytes
+        let input = [0xd9, 0x02, 0xff, 0xfe];
+
+        assert_eq!(json!("//4="), read_msgpack(&input).unwrap());
+    }
+
+    #[test]
+    fn test_cbor_round_trip() {
+        let val = json!({"a": [1, -2, 1.5, "x", null, true], "b": {"c": "d"}});
+
+        let actual = read_cbor(&write_cbor(&val).unwrap()).unwrap();
+
+        assert_eq!(val, actual);
+    }
+
+    #[test]
+    fn test_read_cbor_drops_tags() {
+        // tag 1 (epoch time) 1363896240
+        let input = [0xc1, 0x1a, 0x51, 0x4b, 0x67, 0xb0];
+
+        assert_eq!(json!(1363896240), read_cbor(&input).unwrap());
+    }
+
+    #[test]
+    fn test_read_cbor_unsupported_simple_value() {
+        // [1, simple(16)]
+        let input = [0x82, 0x01, 0xf0];
+
+        assert!(matches!(
+            read_cbor(&input),
+            Err(Error::FormatRead {
+                format: Format::Cbor,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_bson_round_trip_of_several_documents() {
+        let val = json!([{"a": 1, "b": {"$oid": "507f1f77bcf86cd799439011"}}, {"c": "d"}]);
+
+        let actual = read_bson(&write_bson(&val).unwrap()).unwrap();
+
+        assert_eq!(val, actual);
+    }
+
+    #[test]
+    fn test_write_bson_requires_documents() {
+        match write_bson(&json!([{"a": 1}, 2])) {
+            Err(Error::FormatWrite { path, .. }) => assert_eq!("[1]", path.to_string()),
+            other => panic!("Expected a BSON write error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_decode_blob() {
+        assert_eq!(
+            vec![0x81, 0xa1],
+            decode_blob(b"81 a1\n", Encoding::Hex, Format::Msgpack).unwrap()
+        );
+        assert_eq!(
+            b"ab".to_vec(),
+            decode_blob(b"YWI", Encoding::Base64, Format::Msgpack).unwrap()
+        );
+        assert!(decode_blob(b"zz", Encoding::Hex, Format::Msgpack).is_err());
+    }
+}
@@ -1,16 +1,452 @@
+mod align;
+mod markup;
+mod report;
+mod side_by_side;
+
 use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
 
-use console::{Style, style};
+use console::{Style, Term};
+use serde::Serialize;
+use serde_json::Value;
 use similar::{ChangeTag, TextDiff};
 
 use crate::error::Error;
+use crate::path::{JsonPath, PathPattern};
+
+use align::align_arrays;
+pub use markup::{write_html, write_html_differences, write_markdown, write_markdown_differences};
+pub use report::{DiffReport, Hunk};
+pub use side_by_side::write_side_by_side;
+
+/// Match elements of the arrays at `path` by the value of their `key` field, parsed from `path=key`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArrayKey {
+    pub path: PathPattern,
+    pub key: String,
+}
+
+impl FromStr for ArrayKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.rsplit_once('=') {
+            Some((path, key)) if !key.is_empty() => Ok(ArrayKey {
+                path: path.parse()?,
+                key: key.to_string(),
+            }),
+            _ => Err(Error::InvalidPath(format!(
+                "Expected <path>=<key> for array key - {}",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CompareOptions {
+    /// Subtrees at paths matching any of these patterns are left out of the comparison
+    pub ignore: Vec<PathPattern>,
+    /// Identity keys for arrays of records, the first rule matching an array's path is used
+    pub array_keys: Vec<ArrayKey>,
+    /// Pair up elements of arrays without a key rule by similarity instead of position
+    pub match_similar: bool,
+    /// Numbers closer than this are considered equal
+    pub abs_tolerance: f64,
+    /// Numbers whose difference is within this fraction of the larger magnitude are considered equal
+    pub rel_tolerance: f64,
+    /// Treat integer and float representations of the same number as equal, e.g. `1` and `1.0`
+    pub ignore_number_type: bool,
+    /// Treat strings holding a number as that number, e.g. `"1"` and `1`
+    pub numeric_strings: bool,
+}
+
+impl CompareOptions {
+    fn is_ignored(&self, path: &JsonPath) -> bool {
+        self.ignore.iter().any(|pattern| pattern.matches(path))
+    }
+
+    fn is_numeric_lenient(&self) -> bool {
+        self.ignore_number_type || self.abs_tolerance > 0.0 || self.rel_tolerance > 0.0
+    }
+
+    fn as_number(&self, val: &Value) -> Option<f64> {
+        match val {
+            Value::Number(num) => num.as_f64(),
+            Value::String(s) if self.numeric_strings => s.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn as_integer(&self, val: &Value) -> Option<i128> {
+        match val {
+            Value::Number(num) => num
+                .as_i64()
+                .map(i128::from)
+                .or_else(|| num.as_u64().map(i128::from)),
+            Value::String(s) if self.numeric_strings => s.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Whether two scalar values are equal once tolerances and type leniency are applied
+    pub fn values_equal(&self, old: &Value, new: &Value) -> bool {
+        if old == new {
+            return true;
+        }
+        // Without leniency `1` and `1.0` stay different, only a string on either side is coerced
+        let comparable = match (old, new) {
+            (Value::Number(_), Value::Number(_)) => self.is_numeric_lenient(),
+            (Value::String(_), _) | (_, Value::String(_)) => self.numeric_strings,
+            _ => false,
+        };
+        if !comparable {
+            return false;
+        }
+
+        // Integers are compared exactly as f64 loses precision beyond 2^53
+        if let (Some(a), Some(b)) = (self.as_integer(old), self.as_integer(new)) {
+            let diff = (a - b).unsigned_abs() as f64;
+            let larger = a.unsigned_abs().max(b.unsigned_abs()) as f64;
+            return diff <= self.abs_tolerance || diff <= self.rel_tolerance * larger;
+        }
+
+        match (self.as_number(old), self.as_number(new)) {
+            (Some(a), Some(b)) => {
+                let diff = (a - b).abs();
+                diff <= self.abs_tolerance || diff <= self.rel_tolerance * a.abs().max(b.abs())
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A single structural difference between two JSON documents
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "tag", rename_all = "snake_case")]
+pub enum Difference {
+    Added {
+        path: JsonPath,
+        value: Value,
+    },
+    Removed {
+        path: JsonPath,
+        value: Value,
+    },
+    Modified {
+        path: JsonPath,
+        old: Value,
+        new: Value,
+    },
+}
+
+impl Difference {
+    pub fn path(&self) -> &JsonPath {
+        match self {
+            Difference::Added { path, .. }
+            | Difference::Removed { path, .. }
+            | Difference::Modified { path, .. } => path,
+        }
+    }
+}
+
+/// Unit a text diff is computed in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Granularity {
+    #[default]
+    Line,
+    /// Unicode words, so JSON punctuation like `{` and `":` are tokens of their own
+    Word,
+    Char,
+}
+
+impl FromStr for Granularity {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "line" => Ok(Granularity::Line),
+            "word" => Ok(Granularity::Word),
+            "char" => Ok(Granularity::Char),
+            _ => Err(Error::InvalidOption(format!(
+                "Expected line, word or char for granularity - {}",
+                s
+            ))),
+        }
+    }
+}
 
 pub fn compare<'a>(old: &'a str, new: &'a str) -> Result<TextDiff<'a, 'a, 'a, str>, Error> {
-    let diff = TextDiff::from_lines(old, new);
+    compare_by(old, new, Granularity::Line)
+}
+
+/// Text diff split by `granularity`, word and char diffs are rendered with [`write_inline_diff`]
+pub fn compare_by<'a>(
+    old: &'a str,
+    new: &'a str,
+    granularity: Granularity,
+) -> Result<TextDiff<'a, 'a, 'a, str>, Error> {
+    let diff = match granularity {
+        Granularity::Line => TextDiff::from_lines(old, new),
+        Granularity::Word => TextDiff::configure().diff_unicode_words(old, new),
+        Granularity::Char => TextDiff::from_chars(old, new),
+    };
 
     Ok(diff)
 }
 
+/// Whether two JSON values have no differences under `options`
+pub fn is_equal(old: &Value, new: &Value, options: &CompareOptions) -> bool {
+    compare_values(old, new, options).is_empty()
+}
+
+/// Summary counts of a diff, lines for a text diff and paths for a structural one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct DiffStat {
+    pub insertions: usize,
+    pub deletions: usize,
+    pub modifications: usize,
+}
+
+impl DiffStat {
+    pub fn from_text<'a>(diff: &'a TextDiff<'a, 'a, 'a, str>) -> Self {
+        let mut stat = DiffStat::default();
+        for change in diff.iter_all_changes() {
+            match change.tag() {
+                ChangeTag::Insert => stat.insertions += 1,
+                ChangeTag::Delete => stat.deletions += 1,
+                ChangeTag::Equal => {}
+            }
+        }
+
+        stat
+    }
+
+    pub fn from_differences(differences: &[Difference]) -> Self {
+        let mut stat = DiffStat::default();
+        for difference in differences {
+            match difference {
+                Difference::Added { .. } => stat.insertions += 1,
+                Difference::Removed { .. } => stat.deletions += 1,
+                Difference::Modified { .. } => stat.modifications += 1,
+            }
+        }
+
+        stat
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == DiffStat::default()
+    }
+}
+
+impl fmt::Display for DiffStat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plural = |count: usize| if count == 1 { "" } else { "s" };
+
+        write!(
+            f,
+            "{} insertion{}(+), {} deletion{}(-)",
+            self.insertions,
+            plural(self.insertions),
+            self.deletions,
+            plural(self.deletions)
+        )?;
+        if self.modifications > 0 {
+            write!(
+                f,
+                ", {} modification{}(~)",
+                self.modifications,
+                plural(self.modifications)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare two JSON values key by key, returning every difference not excluded by `options`
+pub fn compare_values(old: &Value, new: &Value, options: &CompareOptions) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    diff_value(&JsonPath::default(), old, new, options, &mut differences);
+
+    differences
+}
+
+fn diff_value(
+    path: &JsonPath,
+    old: &Value,
+    new: &Value,
+    options: &CompareOptions,
+    out: &mut Vec<Difference>,
+) {
+    if options.is_ignored(path) {
+        return;
+    }
+
+    match (old, new) {
+        (Value::Object(old_obj), Value::Object(new_obj)) => {
+            for (key, old_val) in old_obj {
+                let child = path.key(key);
+                if options.is_ignored(&child) {
+                    continue;
+                }
+                match new_obj.get(key) {
+                    Some(new_val) => diff_value(&child, old_val, new_val, options, out),
+                    None => out.push(Difference::Removed {
+                        path: child,
+                        value: old_val.clone(),
+                    }),
+                }
+            }
+            for (key, new_val) in new_obj {
+                let child = path.key(key);
+                if !old_obj.contains_key(key) && !options.is_ignored(&child) {
+                    out.push(Difference::Added {
+                        path: child,
+                        value: new_val.clone(),
+                    });
+                }
+            }
+        }
+        (Value::Array(old_arr), Value::Array(new_arr)) => {
+            for pair in align_arrays(path, old_arr, new_arr, options) {
+                match pair {
+                    (Some(old_idx), Some(new_idx)) => {
+                        let child = path.index(old_idx);
+                        if !options.is_ignored(&child) {
+                            diff_value(&child, &old_arr[old_idx], &new_arr[new_idx], options, out)
+                        }
+                    }
+                    (Some(old_idx), None) => {
+                        let child = path.index(old_idx);
+                        if !options.is_ignored(&child) {
+                            out.push(Difference::Removed {
+                                path: child,
+                                value: old_arr[old_idx].clone(),
+                            })
+                        }
+                    }
+                    (None, Some(new_idx)) => {
+                        let child = path.index(new_idx);
+                        if !options.is_ignored(&child) {
+                            out.push(Difference::Added {
+                                path: child,
+                                value: new_arr[new_idx].clone(),
+                            })
+                        }
+                    }
+                    (None, None) => {}
+                }
+            }
+        }
+        (old, new) if options.values_equal(old, new) => {}
+        (old, new) => out.push(Difference::Modified {
+            path: path.clone(),
+            old: old.clone(),
+            new: new.clone(),
+        }),
+    }
+}
+
+/// Rewrite both documents so a textual diff of them only shows what `options` considers a change.
+///
+/// Ignored subtrees are dropped and arrays in `new` are reordered to line up with `old`.
+pub fn normalize(old: &mut Value, new: &mut Value, options: &CompareOptions) {
+    normalize_value(&JsonPath::default(), old, new, options);
+}
+
+fn normalize_value(path: &JsonPath, old: &mut Value, new: &mut Value, options: &CompareOptions) {
+    if options.is_ignored(path) {
+        *old = Value::Null;
+        *new = Value::Null;
+        return;
+    }
+
+    match (old, new) {
+        (Value::Object(old_obj), Value::Object(new_obj)) => {
+            old_obj.retain(|key, _| !options.is_ignored(&path.key(key)));
+            new_obj.retain(|key, _| !options.is_ignored(&path.key(key)));
+            for (key, old_val) in old_obj.iter_mut() {
+                match new_obj.get_mut(key) {
+                    Some(new_val) => normalize_value(&path.key(key), old_val, new_val, options),
+                    None => remove_ignored(old_val, &path.key(key), options),
+                }
+            }
+            for (key, new_val) in new_obj.iter_mut() {
+                if !old_obj.contains_key(key) {
+                    remove_ignored(new_val, &path.key(key), options);
+                }
+            }
+        }
+        (Value::Array(old_arr), Value::Array(new_arr)) => {
+            let alignment = align_arrays(path, old_arr, new_arr, options);
+            let mut old_vals: Vec<Option<Value>> = old_arr.drain(..).map(Some).collect();
+            let mut new_vals: Vec<Option<Value>> = new_arr.drain(..).map(Some).collect();
+
+            for pair in alignment {
+                let child = path.index(pair.0.or(pair.1).unwrap_or_default());
+                if options.is_ignored(&child) {
+                    continue;
+                }
+                let old_val = pair.0.and_then(|idx| old_vals[idx].take());
+                let new_val = pair.1.and_then(|idx| new_vals[idx].take());
+                match (old_val, new_val) {
+                    (Some(mut old_val), Some(mut new_val)) => {
+                        normalize_value(&child, &mut old_val, &mut new_val, options);
+                        old_arr.push(old_val);
+                        new_arr.push(new_val);
+                    }
+                    (Some(mut old_val), None) => {
+                        remove_ignored(&mut old_val, &child, options);
+                        old_arr.push(old_val);
+                    }
+                    (None, Some(mut new_val)) => {
+                        remove_ignored(&mut new_val, &child, options);
+                        new_arr.push(new_val);
+                    }
+                    (None, None) => {}
+                }
+            }
+        }
+        (old, new) => {
+            // Differences within tolerance are hidden by making both sides identical
+            if options.values_equal(old, new) {
+                *new = old.clone();
+            }
+        }
+    }
+}
+
+fn remove_ignored(val: &mut Value, path: &JsonPath, options: &CompareOptions) {
+    if options.is_ignored(path) {
+        *val = Value::Null;
+        return;
+    }
+
+    match val {
+        Value::Object(obj) => {
+            obj.retain(|key, _| !options.is_ignored(&path.key(key)));
+            for (key, child) in obj.iter_mut() {
+                remove_ignored(child, &path.key(key), options);
+            }
+        }
+        Value::Array(arr) => {
+            let mut idx = 0;
+            arr.retain_mut(|child| {
+                let child_path = path.index(idx);
+                idx += 1;
+                if options.is_ignored(&child_path) {
+                    return false;
+                }
+                remove_ignored(child, &child_path, options);
+                true
+            });
+        }
+        _ => {}
+    }
+}
+
 #[derive(Debug)]
 struct Line(Option<usize>);
 
@@ -23,35 +459,523 @@ impl fmt::Display for Line {
     }
 }
 
-pub fn print_diff<'a>(diff: &'a TextDiff<'a, 'a, 'a, str>) {
+/// How `jtool compare` presents its result
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// Colored terminal output
+    #[default]
+    Text,
+    /// [`DiffReport`] serialized as JSON
+    Json,
+    /// Self-contained HTML page with collapsible hunks
+    Html,
+    /// GitHub-flavoured Markdown
+    Markdown,
+}
+
+impl FromStr for ReportFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(ReportFormat::Text),
+            "json" => Ok(ReportFormat::Json),
+            "html" => Ok(ReportFormat::Html),
+            "markdown" | "md" => Ok(ReportFormat::Markdown),
+            _ => Err(Error::InvalidOption(format!(
+                "Expected text, json, html or markdown for format - {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// When to emit ANSI styles in rendered diffs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Color only when stdout is a terminal and `NO_COLOR` is not set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether output written to stdout should be styled
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Auto => console::colors_enabled(),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+impl FromStr for ColorChoice {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(Error::InvalidOption(format!(
+                "Expected auto, always or never for color - {}",
+                s
+            ))),
+        }
+    }
+}
+
+fn tag_style(tag: ChangeTag, color: bool) -> (&'static str, Style) {
+    let s = Style::new().force_styling(color);
+    match tag {
+        ChangeTag::Delete => ("-", s.red()),
+        ChangeTag::Insert => ("+", s.green()),
+        ChangeTag::Equal => (" ", s.dim()),
+    }
+}
+
+/// Width of the attached terminal, falling back to 80 columns when output is not a terminal
+pub fn terminal_width() -> usize {
+    Term::stdout()
+        .size_checked()
+        .map_or(80, |(_, cols)| cols as usize)
+}
+
+/// Write the diff as a unified view with old and new line numbers, styled when `color` is set
+pub fn write_diff<'a, W: Write>(
+    out: &mut W,
+    diff: &'a TextDiff<'a, 'a, 'a, str>,
+    color: bool,
+) -> io::Result<()> {
+    let dim = Style::new().force_styling(color).dim();
+
     for (idx, group) in diff.grouped_ops(3).iter().enumerate() {
         if idx > 0 {
-            println!("{:-^1$}", "-", 80);
+            writeln!(out, "{:-^1$}", "-", 80)?;
         }
         for op in group {
             for change in diff.iter_inline_changes(op) {
-                let (sign, s) = match change.tag() {
-                    ChangeTag::Delete => ("-", Style::new().red()),
-                    ChangeTag::Insert => ("+", Style::new().green()),
-                    ChangeTag::Equal => (" ", Style::new().dim()),
-                };
-                print!(
+                let (sign, s) = tag_style(change.tag(), color);
+                write!(
+                    out,
                     "{}{} |{}",
-                    style(Line(change.old_index())).dim(),
-                    style(Line(change.new_index())).dim(),
+                    dim.apply_to(Line(change.old_index())),
+                    dim.apply_to(Line(change.new_index())),
                     s.apply_to(sign).bold(),
-                );
+                )?;
                 for (emphasized, value) in change.iter_strings_lossy() {
                     if emphasized {
-                        print!("{}", s.apply_to(value).underlined().on_black());
+                        write!(out, "{}", s.apply_to(value).underlined().on_black())?;
                     } else {
-                        print!("{}", s.apply_to(value));
+                        write!(out, "{}", s.apply_to(value))?;
                     }
                 }
                 if change.missing_newline() {
-                    println!();
+                    writeln!(out)?;
                 }
             }
         }
     }
+
+    Ok(())
+}
+
+/// Print the diff to stdout like [`write_diff`], styled unless colors are disabled, e.g. by
+/// `NO_COLOR`
+pub fn print_diff<'a>(diff: &'a TextDiff<'a, 'a, 'a, str>) {
+    // There is no way to report a failed write, e.g. to a closed pipe, so it is ignored
+    let _ = write_diff(&mut io::stdout(), diff, ColorChoice::Auto.enabled());
+}
+
+/// Write a word or char diff as running text.
+///
+/// Removals are red and insertions green, or wrapped in `[-..-]` and `{+..+}` without color.
+pub fn write_inline_diff<'a, W: Write>(
+    out: &mut W,
+    diff: &'a TextDiff<'a, 'a, 'a, str>,
+    color: bool,
+) -> io::Result<()> {
+    let marker = |tag: ChangeTag| match tag {
+        ChangeTag::Delete => ("[-", "-]"),
+        ChangeTag::Insert => ("{+", "+}"),
+        ChangeTag::Equal => ("", ""),
+    };
+    let s = Style::new().force_styling(color);
+    let mut current = ChangeTag::Equal;
+    let mut ends_with_newline = true;
+
+    for change in diff.iter_all_changes() {
+        let tag = change.tag();
+        if tag != current && !color {
+            write!(out, "{}{}", marker(current).1, marker(tag).0)?;
+        }
+        current = tag;
+
+        let value = change.to_string_lossy();
+        match tag {
+            ChangeTag::Delete => {
+                write!(out, "{}", s.clone().red().strikethrough().apply_to(&value))?
+            }
+            ChangeTag::Insert => {
+                write!(out, "{}", s.clone().green().underlined().apply_to(&value))?
+            }
+            ChangeTag::Equal => write!(out, "{}", value)?,
+        }
+        if !value.is_empty() {
+            ends_with_newline = value.ends_with('\n');
+        }
+    }
+    if !color {
+        write!(out, "{}", marker(current).1)?;
+    }
+    if !ends_with_newline {
+        writeln!(out)?;
+    }
+
+    Ok(())
+}
+
+/// Write one line per structural difference, styled when `color` is set
+pub fn write_differences<W: Write>(
+    out: &mut W,
+    differences: &[Difference],
+    color: bool,
+) -> io::Result<()> {
+    let s = Style::new().force_styling(color);
+
+    for difference in differences {
+        match difference {
+            Difference::Added { path, value } => writeln!(
+                out,
+                "{} {}: {}",
+                s.clone().green().bold().apply_to("+"),
+                path,
+                s.clone().green().apply_to(value)
+            )?,
+            Difference::Removed { path, value } => writeln!(
+                out,
+                "{} {}: {}",
+                s.clone().red().bold().apply_to("-"),
+                path,
+                s.clone().red().apply_to(value)
+            )?,
+            Difference::Modified { path, old, new } => writeln!(
+                out,
+                "{} {}: {} -> {}",
+                s.clone().yellow().bold().apply_to("~"),
+                path,
+                s.clone().red().apply_to(old),
+                s.clone().green().apply_to(new)
+            )?,
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::path::PathSegment;
+
+    fn path(segments: &[&str]) -> JsonPath {
+        JsonPath(
+            segments
+                .iter()
+                .map(|s| match s.parse() {
+                    Ok(idx) => PathSegment::Index(idx),
+                    Err(_) => PathSegment::Key(s.to_string()),
+                })
+                .collect(),
+        )
+    }
+
+    fn options(ignore: &[&str]) -> CompareOptions {
+        CompareOptions {
+            ignore: ignore.iter().map(|p| p.parse().unwrap()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_compare_values_reports_paths() {
+        let old = json!({"a": 1, "b": {"c": [1, 2]}, "d": true});
+        let new = json!({"a": 2, "b": {"c": [1]}, "e": null});
+
+        let actual = compare_values(&old, &new, &CompareOptions::default());
+        let paths: Vec<String> = actual.iter().map(|d| d.path().to_string()).collect();
+
+        assert_eq!(vec!["a", "b.c[1]", "d", "e"], paths);
+        assert!(matches!(actual[1], Difference::Removed { .. }));
+        assert!(matches!(actual[3], Difference::Added { .. }));
+    }
+
+    #[test]
+    fn test_compare_values_ignores_paths() {
+        let old = json!({"timestamp": 1, "items": [{"id": 1, "traceId": "x"}], "meta": {"requestId": "a"}});
+        let new = json!({"timestamp": 2, "items": [{"id": 1, "traceId": "y"}], "meta": {"requestId": "b"}});
+
+        let actual = compare_values(
+            &old,
+            &new,
+            &options(&["timestamp", "items[*].traceId", "**.requestId"]),
+        );
+
+        assert!(actual.is_empty());
+    }
+
+    #[test]
+    fn test_normalize_removes_ignored_subtrees() {
+        let mut old = json!({"timestamp": 1, "items": [{"id": 1, "traceId": "x"}]});
+        let mut new = json!({"timestamp": 2, "items": [{"id": 1, "traceId": "y"}]});
+
+        normalize(
+            &mut old,
+            &mut new,
+            &options(&["timestamp", "items[*].traceId"]),
+        );
+
+        assert_eq!(json!({"items": [{"id": 1}]}), old);
+        assert_eq!(old, new);
+    }
+
+    #[test]
+    fn test_compare_values_matches_array_key() {
+        let old = json!({"items": [{"id": 1, "v": "a"}, {"id": 2, "v": "b"}, {"id": 3, "v": "c"}]});
+        let new = json!({"items": [{"id": 3, "v": "c"}, {"id": 1, "v": "z"}, {"id": 4, "v": "d"}]});
+        let options = CompareOptions {
+            array_keys: vec!["items=id".parse().unwrap()],
+            ..Default::default()
+        };
+
+        let actual = compare_values(&old, &new, &options);
+
+        let expected = vec![
+            Difference::Modified {
+                path: path(&["items", "0", "v"]),
+                old: json!("a"),
+                new: json!("z"),
+            },
+            Difference::Added {
+                path: path(&["items", "2"]),
+                value: json!({"id": 4, "v": "d"}),
+            },
+            Difference::Removed {
+                path: path(&["items", "1"]),
+                value: json!({"id": 2, "v": "b"}),
+            },
+        ];
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_normalize_aligns_arrays_by_key() {
+        let mut old = json!([{"id": 1}, {"id": 2}]);
+        let mut new = json!([{"id": 3}, {"id": 2}, {"id": 1}]);
+        let options = CompareOptions {
+            array_keys: vec!["**=id".parse().unwrap()],
+            ..Default::default()
+        };
+
+        normalize(&mut old, &mut new, &options);
+
+        assert_eq!(json!([{"id": 1}, {"id": 2}]), old);
+        assert_eq!(json!([{"id": 3}, {"id": 1}, {"id": 2}]), new);
+    }
+
+    #[test]
+    fn test_values_equal_with_tolerance() {
+        let options = CompareOptions {
+            abs_tolerance: 1e-9,
+            ..Default::default()
+        };
+
+        assert!(options.values_equal(&json!(0.1 + 0.2), &json!(0.3)));
+        assert!(options.values_equal(&json!(1), &json!(1.0)));
+        assert!(!options.values_equal(&json!(1), &json!(1.1)));
+        assert!(!options.values_equal(&json!("1"), &json!(1)));
+
+        let options = CompareOptions {
+            rel_tolerance: 0.01,
+            ..Default::default()
+        };
+        assert!(options.values_equal(&json!(1000), &json!(1009.5)));
+        assert!(!options.values_equal(&json!(1000), &json!(1011)));
+    }
+
+    #[test]
+    fn test_values_equal_number_types() {
+        let strict = CompareOptions::default();
+        assert!(!strict.values_equal(&json!(1), &json!(1.0)));
+
+        let options = CompareOptions {
+            ignore_number_type: true,
+            ..Default::default()
+        };
+        assert!(options.values_equal(&json!(1), &json!(1.0)));
+        assert!(!options.values_equal(&json!("1"), &json!(1)));
+        assert!(!options.values_equal(&json!(9007199254740993u64), &json!(9007199254740992u64)));
+        assert!(!options.values_equal(&json!(u64::MAX), &json!(u64::MAX - 1)));
+
+        let options = CompareOptions {
+            numeric_strings: true,
+            ..Default::default()
+        };
+        assert!(options.values_equal(&json!("1"), &json!(1)));
+        assert!(options.values_equal(&json!(2.5), &json!(" 2.5")));
+        assert!(!options.values_equal(&json!("9007199254740993"), &json!(9007199254740992u64)));
+        assert!(!options.values_equal(&json!("a"), &json!("b")));
+        assert!(!options.values_equal(&json!("1"), &json!(true)));
+    }
+
+    #[test]
+    fn test_normalize_hides_differences_within_tolerance() {
+        let mut old = json!({"price": 10.000000000001, "qty": 1});
+        let mut new = json!({"price": 10.0, "qty": 2.0});
+        let options = CompareOptions {
+            abs_tolerance: 1e-9,
+            ..Default::default()
+        };
+
+        normalize(&mut old, &mut new, &options);
+
+        assert_eq!(json!({"price": 10.000000000001, "qty": 1}), old);
+        assert_eq!(json!({"price": 10.000000000001, "qty": 2.0}), new);
+        assert_eq!(1, compare_values(&old, &new, &options).len());
+    }
+
+    #[test]
+    fn test_is_equal() {
+        let old = json!({"a": 1, "b": [1, 2]});
+
+        assert!(is_equal(
+            &old,
+            &json!({"b": [1, 2], "a": 1}),
+            &CompareOptions::default()
+        ));
+        assert!(!is_equal(
+            &old,
+            &json!({"a": 1, "b": [2, 1]}),
+            &CompareOptions::default()
+        ));
+        assert!(is_equal(
+            &old,
+            &json!({"a": 2, "b": [1, 2]}),
+            &options(&["a"])
+        ));
+    }
+
+    #[test]
+    fn test_diff_stat() {
+        let diff = compare("a\nb\nc\n", "a\nx\nc\nd\n").unwrap();
+        let stat = DiffStat::from_text(&diff);
+
+        assert_eq!(2, stat.insertions);
+        assert_eq!(1, stat.deletions);
+        assert_eq!("2 insertions(+), 1 deletion(-)", stat.to_string());
+        assert!(DiffStat::from_text(&compare("a\n", "a\n").unwrap()).is_empty());
+
+        let differences = compare_values(
+            &json!({"a": 1, "b": 2}),
+            &json!({"a": 2, "c": 3}),
+            &CompareOptions::default(),
+        );
+        assert_eq!(
+            "1 insertion(+), 1 deletion(-), 1 modification(~)",
+            DiffStat::from_differences(&differences).to_string()
+        );
+    }
+
+    #[test]
+    fn test_write_diff_without_color() {
+        let diff = compare("a\nb\n", "a\nc\n").unwrap();
+        let mut out = Vec::new();
+
+        write_diff(&mut out, &diff, false).unwrap();
+
+        let expected = "1   1    | a\n2        |-b\n    2    |+c\n";
+        assert_eq!(expected, String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn test_write_diff_with_color() {
+        let diff = compare("a\n", "b\n").unwrap();
+        let mut out = Vec::new();
+
+        write_diff(&mut out, &diff, true).unwrap();
+
+        assert!(String::from_utf8(out).unwrap().contains("\u{1b}["));
+    }
+
+    #[test]
+    fn test_compare_minified_by_word() {
+        let diff = compare_by(
+            r#"{"name":"John","age":30}"#,
+            r#"{"name":"Jane","age":30}"#,
+            Granularity::Word,
+        )
+        .unwrap();
+        let mut out = Vec::new();
+
+        write_inline_diff(&mut out, &diff, false).unwrap();
+
+        let expected = "{\"name\":\"[-John-]{+Jane+}\",\"age\":30}\n";
+        assert_eq!(expected, String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn test_compare_minified_by_char() {
+        let diff = compare_by(r#"[1,2,3]"#, r#"[1,4,3,5]"#, Granularity::Char).unwrap();
+        let mut out = Vec::new();
+
+        write_inline_diff(&mut out, &diff, false).unwrap();
+
+        assert_eq!("[1,[-2-]{+4+},3{+,5+}]\n", String::from_utf8(out).unwrap());
+        assert_eq!(3, DiffStat::from_text(&diff).insertions);
+    }
+
+    #[test]
+    fn test_parse_granularity() {
+        assert_eq!(Granularity::Word, "word".parse().unwrap());
+        assert!("sentence".parse::<Granularity>().is_err());
+    }
+
+    #[test]
+    fn test_write_differences_without_color() {
+        let differences = compare_values(
+            &json!({"a": 1, "b": 2}),
+            &json!({"a": 2, "c": 3}),
+            &CompareOptions::default(),
+        );
+        let mut out = Vec::new();
+
+        write_differences(&mut out, &differences, false).unwrap();
+
+        assert_eq!(
+            "~ a: 1 -> 2\n- b: 2\n+ c: 3\n",
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_color_choice() {
+        assert_eq!(ColorChoice::Never, "never".parse().unwrap());
+        assert!(ColorChoice::Always.enabled());
+        assert!(!ColorChoice::Never.enabled());
+        assert!("sometimes".parse::<ColorChoice>().is_err());
+    }
+
+    #[test]
+    fn test_parse_array_key() {
+        let actual: ArrayKey = "a[*].items=id".parse().unwrap();
+
+        assert_eq!("a[*].items", actual.path.to_string());
+        assert_eq!("id", actual.key);
+        assert!("items".parse::<ArrayKey>().is_err());
+        assert!("items=".parse::<ArrayKey>().is_err());
+    }
 }
@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use super::CompareOptions;
+use crate::path::JsonPath;
+
+/// Elements scoring below this are reported as a removal plus an insertion rather than a modification
+const SIMILARITY_THRESHOLD: f64 = 0.5;
+
+/// Pairs of (old index, new index) in display order, `None` marking an element only present on one side
+pub type Alignment = Vec<(Option<usize>, Option<usize>)>;
+
+pub fn align_arrays(
+    path: &JsonPath,
+    old: &[Value],
+    new: &[Value],
+    options: &CompareOptions,
+) -> Alignment {
+    if let Some(rule) = options
+        .array_keys
+        .iter()
+        .find(|rule| rule.path.matches(path))
+    {
+        align_by_key(old, new, &rule.key)
+    } else if options.match_similar {
+        align_by_similarity(old, new)
+    } else {
+        align_by_position(old, new)
+    }
+}
+
+fn align_by_position(old: &[Value], new: &[Value]) -> Alignment {
+    (0..old.len().max(new.len()))
+        .map(|idx| {
+            (
+                (idx < old.len()).then_some(idx),
+                (idx < new.len()).then_some(idx),
+            )
+        })
+        .collect()
+}
+
+fn identity(val: &Value, key: &str) -> Option<String> {
+    val.get(key).map(|id| id.to_string())
+}
+
+fn align_by_key(old: &[Value], new: &[Value], key: &str) -> Alignment {
+    let mut new_by_id: HashMap<String, usize> = HashMap::new();
+    for (idx, val) in new.iter().enumerate().rev() {
+        if let Some(id) = identity(val, key) {
+            new_by_id.insert(id, idx);
+        }
+    }
+
+    // Old elements keep their order, elements without a key only match an identical element
+    let mut matched = vec![false; new.len()];
+    let mut alignment: Alignment = old
+        .iter()
+        .enumerate()
+        .map(|(old_idx, val)| {
+            let partner = match identity(val, key) {
+                Some(id) => new_by_id.remove(&id),
+                None => (0..new.len()).find(|&idx| {
+                    !matched[idx] && identity(&new[idx], key).is_none() && new[idx] == *val
+                }),
+            };
+            if let Some(new_idx) = partner {
+                matched[new_idx] = true;
+            }
+            (Some(old_idx), partner)
+        })
+        .collect();
+
+    // Unmatched new elements go right after their predecessor from the new array
+    for new_idx in (0..new.len()).filter(|&idx| !matched[idx]) {
+        let position = match new_idx {
+            0 => 0,
+            _ => alignment
+                .iter()
+                .position(|(_, n)| *n == Some(new_idx - 1))
+                .map_or(0, |pos| pos + 1),
+        };
+        alignment.insert(position, (None, Some(new_idx)));
+    }
+
+    alignment
+}
+
+/// Score between 0 and 1 of how much of two values is shared
+pub fn similarity(old: &Value, new: &Value) -> f64 {
+    match (old, new) {
+        (old, new) if old == new => 1.0,
+        (Value::Object(old_obj), Value::Object(new_obj)) => {
+            let union =
+                old_obj.len() + new_obj.keys().filter(|k| !old_obj.contains_key(*k)).count();
+            let shared: f64 = old_obj
+                .iter()
+                .filter_map(|(key, val)| new_obj.get(key).map(|other| similarity(val, other)))
+                .sum();
+            shared / union as f64
+        }
+        (Value::Array(old_arr), Value::Array(new_arr)) => {
+            let len = old_arr.len().max(new_arr.len());
+            let shared: f64 = old_arr
+                .iter()
+                .zip(new_arr)
+                .map(|(a, b)| similarity(a, b))
+                .sum();
+            shared / len as f64
+        }
+        _ => 0.0,
+    }
+}
+
+/// Edit distance alignment where pairing two elements costs how dissimilar they are
+fn align_by_similarity(old: &[Value], new: &[Value]) -> Alignment {
+    let (n, m) = (old.len(), new.len());
+    let scores: Vec<Vec<f64>> = old
+        .iter()
+        .map(|a| new.iter().map(|b| similarity(a, b)).collect())
+        .collect();
+
+    let mut cost = vec![vec![0.0; m + 1]; n + 1];
+    for (i, row) in cost.iter_mut().enumerate() {
+        row[0] = i as f64;
+    }
+    for (j, cell) in cost[0].iter_mut().enumerate() {
+        *cell = j as f64;
+    }
+    for i in 1..=n {
+        for j in 1..=m {
+            let mut best = (cost[i - 1][j] + 1.0).min(cost[i][j - 1] + 1.0);
+            if scores[i - 1][j - 1] >= SIMILARITY_THRESHOLD {
+                best = best.min(cost[i - 1][j - 1] + 1.0 - scores[i - 1][j - 1]);
+            }
+            cost[i][j] = best;
+        }
+    }
+
+    let mut alignment = Alignment::new();
+    let (mut i, mut j) = (n, m);
+    while i > 0 || j > 0 {
+        if i > 0
+            && j > 0
+            && scores[i - 1][j - 1] >= SIMILARITY_THRESHOLD
+            && cost[i][j] == cost[i - 1][j - 1] + 1.0 - scores[i - 1][j - 1]
+        {
+            alignment.push((Some(i - 1), Some(j - 1)));
+            i -= 1;
+            j -= 1;
+        } else if i > 0 && cost[i][j] == cost[i - 1][j] + 1.0 {
+            alignment.push((Some(i - 1), None));
+            i -= 1;
+        } else {
+            alignment.push((None, Some(j - 1)));
+            j -= 1;
+        }
+    }
+    alignment.reverse();
+
+    alignment
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn values(val: Value) -> Vec<Value> {
+        val.as_array().unwrap().clone()
+    }
+
+    #[test]
+    fn test_align_by_key_ignores_order() {
+        let old = values(json!([{"id": 1}, {"id": 2}, {"id": 3}]));
+        let new = values(json!([{"id": 3}, {"id": 4}, {"id": 1}]));
+
+        let actual = align_by_key(&old, &new, "id");
+
+        let expected = vec![
+            (Some(0), Some(2)),
+            (Some(1), None),
+            (Some(2), Some(0)),
+            (None, Some(1)),
+        ];
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_align_by_similarity_detects_insertion() {
+        let old = values(json!([{"a": 1, "b": 1}, {"a": 2, "b": 2}]));
+        let new = values(json!([{"x": 0}, {"a": 1, "b": 1}, {"a": 2, "b": 3}]));
+
+        let actual = align_by_similarity(&old, &new);
+
+        let expected = vec![(None, Some(0)), (Some(0), Some(1)), (Some(1), Some(2))];
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(1.0, similarity(&json!({"a": 1}), &json!({"a": 1})));
+        assert_eq!(
+            0.5,
+            similarity(&json!({"a": 1, "b": 2}), &json!({"a": 1, "b": 3}))
+        );
+        assert_eq!(0.0, similarity(&json!("a"), &json!(1)));
+    }
+}
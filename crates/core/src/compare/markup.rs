@@ -0,0 +1,276 @@
+use std::io::{self, Write};
+
+use similar::{ChangeTag, DiffOp, TextDiff};
+
+use super::{DiffStat, Difference};
+use crate::markdown::{backtick_fence, code_span};
+
+const HTML_STYLE: &str = "body{font-family:sans-serif;margin:1em}\
+summary{font-family:monospace;cursor:pointer;color:#555;padding:4px 0}\
+table{border-collapse:collapse;width:100%;font-family:monospace;font-size:13px}\
+td{padding:0 6px;vertical-align:top}\
+td.ln{color:#999;text-align:right;user-select:none;width:1%}\
+td.code{white-space:pre-wrap;word-break:break-all}\
+tr.delete{background:#ffebe9}tr.insert{background:#e6ffec}\
+tr.delete mark{background:#ffc1c0}tr.insert mark{background:#abf2bc}\
+tr.modified{background:#fff8c5}";
+
+fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            ch => out.push(ch),
+        }
+    }
+
+    out
+}
+
+/// Unified diff style header with one-based line numbers, e.g. `@@ -1,4 +1,6 @@`
+fn hunk_header(group: &[DiffOp]) -> String {
+    let (Some(first), Some(last)) = (group.first(), group.last()) else {
+        return String::from("@@ @@");
+    };
+    let old_start = first.old_range().start;
+    let new_start = first.new_range().start;
+
+    format!(
+        "@@ -{},{} +{},{} @@",
+        old_start + 1,
+        last.old_range().end - old_start,
+        new_start + 1,
+        last.new_range().end - new_start
+    )
+}
+
+fn tag_class(tag: ChangeTag) -> (&'static str, &'static str) {
+    match tag {
+        ChangeTag::Delete => ("delete", "-"),
+        ChangeTag::Insert => ("insert", "+"),
+        ChangeTag::Equal => ("equal", " "),
+    }
+}
+
+fn write_html_document<W: Write>(
+    out: &mut W,
+    stat: &DiffStat,
+    body: impl FnOnce(&mut W) -> io::Result<()>,
+) -> io::Result<()> {
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html>")?;
+    writeln!(
+        out,
+        "<head><meta charset=\"utf-8\"><title>jtool diff</title><style>{}</style></head>",
+        HTML_STYLE
+    )?;
+    writeln!(out, "<body>")?;
+    writeln!(out, "<p>{}</p>", escape_html(&stat.to_string()))?;
+    body(out)?;
+    writeln!(out, "</body>")?;
+    writeln!(out, "</html>")
+}
+
+/// Write a line diff as a self-contained HTML page with one collapsible section per hunk
+pub fn write_html<'a, W: Write>(
+    out: &mut W,
+    diff: &'a TextDiff<'a, 'a, 'a, str>,
+) -> io::Result<()> {
+    write_html_document(out, &DiffStat::from_text(diff), |out| {
+        for group in diff.grouped_ops(3) {
+            writeln!(
+                out,
+                "<details open><summary>{}</summary><table>",
+                hunk_header(&group)
+            )?;
+            for op in &group {
+                for change in diff.iter_inline_changes(op) {
+                    let (class, sign) = tag_class(change.tag());
+                    let line =
+                        |idx: Option<usize>| idx.map_or(String::new(), |i| (i + 1).to_string());
+                    write!(
+                        out,
+                        "<tr class=\"{}\"><td class=\"ln\">{}</td><td class=\"ln\">{}</td><td>{}</td><td class=\"code\">",
+                        class,
+                        line(change.old_index()),
+                        line(change.new_index()),
+                        sign
+                    )?;
+                    for (emphasized, value) in change.iter_strings_lossy() {
+                        let value = escape_html(value.trim_end_matches(['\n', '\r']));
+                        if emphasized {
+                            write!(out, "<mark>{}</mark>", value)?;
+                        } else {
+                            write!(out, "{}", value)?;
+                        }
+                    }
+                    writeln!(out, "</td></tr>")?;
+                }
+            }
+            writeln!(out, "</table></details>")?;
+        }
+        Ok(())
+    })
+}
+
+/// Write structural differences as a self-contained HTML page with one table row per path
+pub fn write_html_differences<W: Write>(out: &mut W, differences: &[Difference]) -> io::Result<()> {
+    write_html_document(out, &DiffStat::from_differences(differences), |out| {
+        writeln!(out, "<table>")?;
+        writeln!(
+            out,
+            "<tr><th></th><th>Path</th><th>Old</th><th>New</th></tr>"
+        )?;
+        for difference in differences {
+            let (class, sign, old, new) = difference_cells(difference);
+            writeln!(
+                out,
+                "<tr class=\"{}\"><td>{}</td><td>{}</td><td class=\"code\">{}</td><td class=\"code\">{}</td></tr>",
+                class,
+                sign,
+                escape_html(&difference.path().to_string()),
+                escape_html(&old),
+                escape_html(&new)
+            )?;
+        }
+        writeln!(out, "</table>")
+    })
+}
+
+fn difference_cells(difference: &Difference) -> (&'static str, &'static str, String, String) {
+    match difference {
+        Difference::Added { value, .. } => ("insert", "+", String::new(), value.to_string()),
+        Difference::Removed { value, .. } => ("delete", "-", value.to_string(), String::new()),
+        Difference::Modified { old, new, .. } => {
+            ("modified", "~", old.to_string(), new.to_string())
+        }
+    }
+}
+
+/// Write a line diff as GitHub-flavoured Markdown, one `diff` code block per hunk
+pub fn write_markdown<'a, W: Write>(
+    out: &mut W,
+    diff: &'a TextDiff<'a, 'a, 'a, str>,
+) -> io::Result<()> {
+    writeln!(out, "**{}**", DiffStat::from_text(diff))?;
+
+    for group in diff.grouped_ops(3) {
+        let mut block = format!("{}\n", hunk_header(&group));
+        for op in &group {
+            for change in diff.iter_changes(op) {
+                let (_, sign) = tag_class(change.tag());
+                block.push_str(sign);
+                block.push_str(change.value().trim_end_matches(['\n', '\r']));
+                block.push('\n');
+            }
+        }
+
+        let fence = backtick_fence(&block, 3);
+        writeln!(out)?;
+        write!(out, "{}diff\n{}{}\n", fence, block, fence)?;
+    }
+
+    Ok(())
+}
+
+/// Write structural differences as a GitHub-flavoured Markdown table
+pub fn write_markdown_differences<W: Write>(
+    out: &mut W,
+    differences: &[Difference],
+) -> io::Result<()> {
+    writeln!(out, "**{}**", DiffStat::from_differences(differences))?;
+    if differences.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(out)?;
+    writeln!(out, "| | Path | Old | New |")?;
+    writeln!(out, "|---|---|---|---|")?;
+    for difference in differences {
+        let (_, sign, old, new) = difference_cells(difference);
+        writeln!(
+            out,
+            "| {} | {} | {} | {} |",
+            sign,
+            code_span(&difference.path().to_string()),
+            code_span(&old),
+            code_span(&new)
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::compare::{CompareOptions, compare, compare_values};
+
+    fn render(f: impl FnOnce(&mut Vec<u8>) -> io::Result<()>) -> String {
+        let mut out = Vec::new();
+        f(&mut out).unwrap();
+
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_write_html_escapes_and_highlights() {
+        let diff = compare("<a>\n\"b\"\n", "<a>\n\"c\"\n").unwrap();
+
+        let actual = render(|out| write_html(out, &diff));
+
+        assert!(actual.starts_with("<!DOCTYPE html>"));
+        assert!(actual.contains("<details open><summary>@@ -1,2 +1,2 @@</summary>"));
+        assert!(actual.contains("&lt;a&gt;"));
+        assert!(
+            actual.contains("<tr class=\"delete\"><td class=\"ln\">2</td><td class=\"ln\"></td>")
+        );
+        assert!(actual.contains("&quot;<mark>b</mark>&quot;"));
+    }
+
+    #[test]
+    fn test_write_markdown() {
+        let diff = compare("a\nb\n", "a\nc\n").unwrap();
+
+        let actual = render(|out| write_markdown(out, &diff));
+
+        let expected =
+            "**1 insertion(+), 1 deletion(-)**\n\n```diff\n@@ -1,2 +1,2 @@\n a\n-b\n+c\n```\n";
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_write_markdown_fence_longer_than_content() {
+        let diff = compare("```\n", "````\n").unwrap();
+
+        let actual = render(|out| write_markdown(out, &diff));
+
+        assert!(actual.contains("\n`````diff\n"));
+        assert!(actual.ends_with("\n`````\n"));
+    }
+
+    #[test]
+    fn test_write_markdown_differences() {
+        let differences = compare_values(
+            &json!({"a": "x|y", "b": 1}),
+            &json!({"a": "z", "c": 2}),
+            &CompareOptions::default(),
+        );
+
+        let actual = render(|out| write_markdown_differences(out, &differences));
+
+        let expected = "**1 insertion(+), 1 deletion(-), 1 modification(~)**\n\n\
+            | | Path | Old | New |\n\
+            |---|---|---|---|\n\
+            | ~ | ` a ` | ` \"x\\|y\" ` | ` \"z\" ` |\n\
+            | - | ` b ` | ` 1 ` |  |\n\
+            | + | ` c ` |  | ` 2 ` |\n";
+        assert_eq!(expected, actual);
+    }
+}
@@ -0,0 +1,122 @@
+use serde::Serialize;
+use similar::{Change, TextDiff};
+
+use super::{DiffStat, Difference};
+
+/// Lines of a text diff around one cluster of changes, ranges are zero-based like change indices
+#[derive(Debug, Clone, Serialize)]
+pub struct Hunk<'a> {
+    pub old_start: usize,
+    pub old_len: usize,
+    pub new_start: usize,
+    pub new_len: usize,
+    pub changes: Vec<Change<&'a str>>,
+}
+
+/// Machine-readable result of a compare, holding hunks for a text diff or differences for a
+/// structural one
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffReport<'a> {
+    pub equal: bool,
+    pub stat: DiffStat,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hunks: Option<Vec<Hunk<'a>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub differences: Option<&'a [Difference]>,
+}
+
+impl<'a> DiffReport<'a> {
+    pub fn from_text(diff: &'a TextDiff<'a, 'a, 'a, str>) -> Self {
+        let hunks: Vec<Hunk<'a>> = diff
+            .grouped_ops(3)
+            .iter()
+            .filter_map(|group| {
+                let (first, last) = (group.first()?, group.last()?);
+                let old_start = first.old_range().start;
+                let new_start = first.new_range().start;
+
+                Some(Hunk {
+                    old_start,
+                    old_len: last.old_range().end - old_start,
+                    new_start,
+                    new_len: last.new_range().end - new_start,
+                    changes: group.iter().flat_map(|op| diff.iter_changes(op)).collect(),
+                })
+            })
+            .collect();
+        let stat = DiffStat::from_text(diff);
+
+        DiffReport {
+            equal: stat.is_empty(),
+            stat,
+            hunks: Some(hunks),
+            differences: None,
+        }
+    }
+
+    pub fn from_differences(differences: &'a [Difference]) -> Self {
+        let stat = DiffStat::from_differences(differences);
+
+        DiffReport {
+            equal: stat.is_empty(),
+            stat,
+            hunks: None,
+            differences: Some(differences),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::compare::{CompareOptions, compare, compare_values};
+
+    #[test]
+    fn test_text_report() {
+        let diff = compare("a\nb\nc\n", "a\nx\nc\n").unwrap();
+
+        let actual = serde_json::to_value(DiffReport::from_text(&diff)).unwrap();
+
+        let expected = json!({
+            "equal": false,
+            "stat": {"insertions": 1, "deletions": 1, "modifications": 0},
+            "hunks": [{
+                "old_start": 0,
+                "old_len": 3,
+                "new_start": 0,
+                "new_len": 3,
+                "changes": [
+                    {"tag": "equal", "old_index": 0, "new_index": 0, "value": "a\n"},
+                    {"tag": "delete", "old_index": 1, "new_index": null, "value": "b\n"},
+                    {"tag": "insert", "old_index": null, "new_index": 1, "value": "x\n"},
+                    {"tag": "equal", "old_index": 2, "new_index": 2, "value": "c\n"},
+                ],
+            }],
+        });
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_structural_report() {
+        let differences = compare_values(
+            &json!({"a": [1], "b": 1}),
+            &json!({"a": [2], "c": 1}),
+            &CompareOptions::default(),
+        );
+
+        let actual = serde_json::to_value(DiffReport::from_differences(&differences)).unwrap();
+
+        let expected = json!({
+            "equal": false,
+            "stat": {"insertions": 1, "deletions": 1, "modifications": 1},
+            "differences": [
+                {"tag": "modified", "path": "a[0]", "old": 1, "new": 2},
+                {"tag": "removed", "path": "b", "value": 1},
+                {"tag": "added", "path": "c", "value": 1},
+            ],
+        });
+        assert_eq!(expected, actual);
+    }
+}
@@ -0,0 +1,211 @@
+use std::io::{self, Write};
+
+use console::{Style, measure_text_width};
+use similar::{ChangeTag, DiffTag, InlineChange, TextDiff};
+
+use super::{Line, tag_style};
+
+/// Columns taken by the line number and sign in front of each side
+const GUTTER_WIDTH: usize = 6;
+const SEPARATOR: &str = " │ ";
+
+/// Text of one side of a row, as (emphasized, value) segments
+type Segments = Vec<(bool, String)>;
+
+struct Cell {
+    tag: ChangeTag,
+    index: Option<usize>,
+    lines: Vec<Segments>,
+}
+
+impl Cell {
+    fn new(change: &InlineChange<'_, str>, index: Option<usize>, width: usize) -> Self {
+        let segments: Segments = change
+            .iter_strings_lossy()
+            .map(|(emphasized, value)| {
+                (emphasized, value.trim_end_matches(['\n', '\r']).to_string())
+            })
+            .collect();
+
+        Cell {
+            tag: change.tag(),
+            index,
+            lines: wrap(segments, width),
+        }
+    }
+}
+
+/// Split segments into lines no wider than `width` columns, keeping emphasis on each piece
+fn wrap(segments: Segments, width: usize) -> Vec<Segments> {
+    let mut lines = vec![Vec::new()];
+    let mut used = 0;
+
+    for (emphasized, value) in segments {
+        let mut current = String::new();
+        for ch in value.chars() {
+            let ch_width = measure_text_width(ch.encode_utf8(&mut [0; 4]));
+            if used + ch_width > width && used > 0 {
+                if !current.is_empty() {
+                    lines
+                        .last_mut()
+                        .unwrap()
+                        .push((emphasized, std::mem::take(&mut current)));
+                }
+                lines.push(Vec::new());
+                used = 0;
+            }
+            current.push(ch);
+            used += ch_width;
+        }
+        if !current.is_empty() {
+            lines.last_mut().unwrap().push((emphasized, current));
+        }
+    }
+
+    lines
+}
+
+fn render_cell(cell: Option<&Cell>, line: usize, width: usize, color: bool) -> String {
+    let Some(cell) = cell else {
+        return " ".repeat(GUTTER_WIDTH + width);
+    };
+    let (sign, s) = tag_style(cell.tag, color);
+    let Some(segments) = cell.lines.get(line) else {
+        return " ".repeat(GUTTER_WIDTH + width);
+    };
+
+    let mut out = match line {
+        0 => format!(
+            "{} {}",
+            Style::new()
+                .force_styling(color)
+                .dim()
+                .apply_to(Line(cell.index)),
+            s.apply_to(sign).bold()
+        ),
+        _ => " ".repeat(GUTTER_WIDTH),
+    };
+    let mut used = 0;
+    for (emphasized, value) in segments {
+        used += measure_text_width(value);
+        if *emphasized {
+            out.push_str(&s.apply_to(value).underlined().on_black().to_string());
+        } else {
+            out.push_str(&s.apply_to(value).to_string());
+        }
+    }
+    out.push_str(&" ".repeat(width.saturating_sub(used)));
+
+    out
+}
+
+/// Write the diff as two columns, old on the left and new on the right, fitted to `width` columns
+pub fn write_side_by_side<'a, W: Write>(
+    out: &mut W,
+    diff: &'a TextDiff<'a, 'a, 'a, str>,
+    width: usize,
+    color: bool,
+) -> io::Result<()> {
+    let column = (width.saturating_sub(measure_text_width(SEPARATOR)) / 2).max(GUTTER_WIDTH + 1);
+    let content = column - GUTTER_WIDTH;
+
+    for (idx, group) in diff.grouped_ops(3).iter().enumerate() {
+        if idx > 0 {
+            writeln!(out, "{:-^1$}", "-", width)?;
+        }
+        for op in group {
+            let changes: Vec<_> = diff.iter_inline_changes(op).collect();
+            let rows: Vec<(Option<Cell>, Option<Cell>)> = match op.tag() {
+                DiffTag::Equal => changes
+                    .iter()
+                    .map(|change| {
+                        (
+                            Some(Cell::new(change, change.old_index(), content)),
+                            Some(Cell::new(change, change.new_index(), content)),
+                        )
+                    })
+                    .collect(),
+                _ => {
+                    let mut old: Vec<Cell> = changes
+                        .iter()
+                        .filter(|change| change.tag() == ChangeTag::Delete)
+                        .map(|change| Cell::new(change, change.old_index(), content))
+                        .collect();
+                    let mut new: Vec<Cell> = changes
+                        .iter()
+                        .filter(|change| change.tag() == ChangeTag::Insert)
+                        .map(|change| Cell::new(change, change.new_index(), content))
+                        .collect();
+                    let len = old.len().max(new.len());
+                    old.reverse();
+                    new.reverse();
+                    (0..len).map(|_| (old.pop(), new.pop())).collect()
+                }
+            };
+
+            for (left, right) in rows {
+                let height = left
+                    .iter()
+                    .chain(right.iter())
+                    .map(|cell| cell.lines.len())
+                    .max()
+                    .unwrap_or(1);
+                for line in 0..height {
+                    writeln!(
+                        out,
+                        "{}{}{}",
+                        render_cell(left.as_ref(), line, content, color),
+                        Style::new().force_styling(color).dim().apply_to(SEPARATOR),
+                        render_cell(right.as_ref(), line, content, color)
+                    )?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain(lines: Vec<Segments>) -> Vec<String> {
+        lines
+            .into_iter()
+            .map(|line| line.into_iter().map(|(_, value)| value).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_wrap_long_line() {
+        let segments = vec![(false, "abcdef".to_string()), (true, "ghij".to_string())];
+
+        let actual = wrap(segments, 4);
+
+        assert_eq!(vec!["abcd", "efgh", "ij"], plain(actual.clone()));
+        assert_eq!(
+            vec![(false, "ef".to_string()), (true, "gh".to_string())],
+            actual[1]
+        );
+    }
+
+    #[test]
+    fn test_write_side_by_side_without_color() {
+        let diff = TextDiff::from_lines("a\nb\n", "a\nc\n");
+        let mut out = Vec::new();
+
+        write_side_by_side(&mut out, &diff, 17, false).unwrap();
+
+        let expected = "1     a │ 1     a\n2    -b │ 2    +c\n";
+        assert_eq!(expected, String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn test_wrap_short_line() {
+        let actual = wrap(vec![(false, "ab".to_string())], 4);
+
+        assert_eq!(vec!["ab"], plain(actual));
+        assert_eq!(vec![Vec::<(bool, String)>::new()], wrap(Vec::new(), 4));
+    }
+}
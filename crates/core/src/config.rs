@@ -0,0 +1,243 @@
+//! Configuration read from a user-level `config.toml` under the XDG config directory, e.g.
+//! `~/.config/jtool/config.toml`, and a project-level `jtool.toml` found by walking up from the
+//! current directory, the project file taking precedence key by key:
+//!
+//! ```toml
+//! [paths]
+//! payload = ["body", "headers.meta"]
+//!
+//! [stringify]
+//! paths = ["@payload"]
+//! missing = "skip"
+//!
+//! [compare]
+//! ignore = ["**.updated_at"]
+//! ```
+//!
+//! Every table other than `paths` holds defaults for the subcommand it is named after, keyed by
+//! long option name. Flags turned on here can be turned off for one run, e.g. `--prettify=false`,
+//! and unknown tables and options are skipped with a warning.
+//!
+//! Path sets double as stringify profiles, e.g. `stringify --profile sns`, and sets for common
+//! message envelopes are built in, see [`builtin_paths`].
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+pub const PROJECT_FILE: &str = "jtool.toml";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Config {
+    /// Named sets of key paths, referenced as `@name` wherever paths are given
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub paths: BTreeMap<String, Vec<String>>,
+    /// Option defaults by subcommand and long option name, e.g. `[parse] prettify = true`
+    #[serde(flatten)]
+    pub commands: BTreeMap<String, BTreeMap<String, toml::Value>>,
+}
+
+impl Config {
+    /// Configuration with only the built-in path sets
+    pub fn builtin() -> Self {
+        Config {
+            paths: builtin_paths(),
+            ..Config::default()
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let invalid = |reason: String| Error::Config {
+            path: path.to_path_buf(),
+            reason,
+        };
+
+        let input = fs::read_to_string(path).map_err(|err| invalid(err.to_string()))?;
+        toml::from_str(&input).map_err(|err| invalid(err.message().to_string()))
+    }
+
+    /// Load the user config and the project config above `cwd` over the built-in path sets,
+    /// returning the files read
+    pub fn discover(cwd: &Path) -> Result<(Self, Vec<PathBuf>), Error> {
+        let candidates = [user_config_path(), find_project_config(cwd)];
+
+        let mut config = Config::builtin();
+        let mut sources = Vec::new();
+        for path in candidates.into_iter().flatten() {
+            if path.is_file() {
+                config.merge(Config::load(&path)?);
+                sources.push(path);
+            }
+        }
+
+        Ok((config, sources))
+    }
+
+    /// Overlay `other`, its path sets and option defaults replacing those of the same name
+    pub fn merge(&mut self, other: Config) {
+        self.paths.extend(other.paths);
+        for (command, options) in other.commands {
+            self.commands.entry(command).or_default().extend(options);
+        }
+    }
+
+    pub fn path_set(&self, name: &str) -> Result<&[String], Error> {
+        self.paths.get(name).map(Vec::as_slice).ok_or_else(|| {
+            let names: Vec<&str> = self.paths.keys().map(String::as_str).collect();
+            Error::InvalidOption(format!(
+                "Expected one of {} for path set - {}",
+                names.join(", "),
+                name
+            ))
+        })
+    }
+
+    /// Replace `@name` references with the paths of that set, keeping other paths as they are
+    pub fn expand_paths(&self, paths: &[String]) -> Result<Vec<String>, Error> {
+        let mut expanded = Vec::new();
+        for path in paths {
+            match path.strip_prefix('@') {
+                Some(name) => expanded.extend(self.path_set(name)?.iter().cloned()),
+                None => expanded.push(path.clone()),
+            }
+        }
+
+        Ok(expanded)
+    }
+
+    pub fn to_toml(&self) -> Result<String, Error> {
+        toml::to_string_pretty(self).map_err(|err| Error::Config {
+            path: PathBuf::new(),
+            reason: err.to_string(),
+        })
+    }
+}
+
+/// Fields that upstream systems always deliver stringified:
+///
+/// - `api-gateway`: the `body` of an API Gateway proxy event or response
+/// - `sns`: the `Message` of an SNS notification
+/// - `sqs`: the `body` of an SQS message
+/// - `kafka`: the `key` and `value` of a Kafka record as exported by most consumers
+pub fn builtin_paths() -> BTreeMap<String, Vec<String>> {
+    let sets: [(&str, &[&str]); 4] = [
+        ("api-gateway", &["body"]),
+        ("sns", &["Message"]),
+        ("sqs", &["body"]),
+        ("kafka", &["key", "value"]),
+    ];
+
+    sets.into_iter()
+        .map(|(name, paths)| {
+            let paths = paths.iter().map(|path| path.to_string()).collect();
+            (name.to_string(), paths)
+        })
+        .collect()
+}
+
+/// Command line values of an option default, an array giving one value per element
+pub fn option_values(val: &toml::Value) -> Vec<String> {
+    match val {
+        toml::Value::String(str) => vec![str.clone()],
+        toml::Value::Array(arr) => arr.iter().flat_map(option_values).collect(),
+        val => vec![val.to_string()],
+    }
+}
+
+/// `jtool/config.toml` under `$XDG_CONFIG_HOME` or the platform's config directory
+pub fn user_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("jtool").join("config.toml"))
+}
+
+/// Nearest [`PROJECT_FILE`] in `start` or one of its ancestors
+pub fn find_project_config(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_FILE))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(input: &str) -> Config {
+        toml::from_str(input).unwrap()
+    }
+
+    #[test]
+    fn test_merge_prefers_later_config() {
+        let mut actual =
+            config("[paths]\na = [\"x\"]\n[parse]\nprettify = true\nfrom = \"yaml\"\n");
+
+        actual.merge(config("[paths]\na = [\"y\"]\n[parse]\nfrom = \"toml\"\n"));
+
+        let expected = config("[paths]\na = [\"y\"]\n[parse]\nprettify = true\nfrom = \"toml\"\n");
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_expand_paths() {
+        let config = config("[paths]\npayload = [\"body\", \"headers.meta\"]\n");
+        let paths = vec![String::from("@payload"), String::from("id")];
+
+        let actual = config.expand_paths(&paths).unwrap();
+
+        assert_eq!(vec!["body", "headers.meta", "id"], actual);
+        assert!(matches!(
+            config.expand_paths(&[String::from("@missing")]),
+            Err(Error::InvalidOption(_))
+        ));
+    }
+
+    #[test]
+    fn test_builtin_path_sets_can_be_overridden() {
+        let mut actual = Config::builtin();
+        assert_eq!(vec!["Message"], actual.path_set("sns").unwrap());
+
+        actual.merge(config("[paths]\nsns = [\"Message\", \"Subject\"]\n"));
+
+        assert_eq!(vec!["Message", "Subject"], actual.path_set("sns").unwrap());
+        assert_eq!(vec!["key", "value"], actual.path_set("kafka").unwrap());
+        assert!(matches!(
+            actual.path_set("nope"),
+            Err(Error::InvalidOption(_))
+        ));
+    }
+
+    #[test]
+    fn test_option_values() {
+        let config =
+            config("[compare]\nignore = [\"a\", \"b\"]\nabs-tolerance = 0.5\nquiet = true\n");
+        let options = &config.commands["compare"];
+
+        assert_eq!(vec!["a", "b"], option_values(&options["ignore"]));
+        assert_eq!(vec!["0.5"], option_values(&options["abs-tolerance"]));
+        assert_eq!(vec!["true"], option_values(&options["quiet"]));
+    }
+
+    #[test]
+    fn test_find_project_config_walks_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("a").join("b");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(dir.path().join(PROJECT_FILE), "[paths]\n").unwrap();
+
+        let actual = find_project_config(&nested);
+
+        assert_eq!(Some(dir.path().join(PROJECT_FILE)), actual);
+    }
+
+    #[test]
+    fn test_load_invalid_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(PROJECT_FILE);
+        fs::write(&path, "parse = 1\n").unwrap();
+
+        assert!(matches!(Config::load(&path), Err(Error::Config { .. })));
+    }
+}
@@ -1,28 +1,60 @@
-use std::error::Error as StdError;
-use std::fmt;
+use std::io;
+use std::path::PathBuf;
 
-#[derive(Debug)]
-pub enum Error {
-    JSONParsing(serde_json::Error),
-    JSONStringify(String),
-    NotImplemented,
-}
+use crate::format::Format;
+use crate::path::JsonPath;
 
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Error::JSONParsing(err) => write!(f, "Error while parsing JSON {}", err),
-            Error::JSONStringify(msg) => write!(f, "Error while stringifying JSON: {}", msg),
-            Error::NotImplemented => write!(f, "Not implemented yet"),
-        }
-    }
-}
-
-impl StdError for Error {
-    fn source(&self) -> Option<&(dyn StdError + 'static)> {
-        match self {
-            Error::JSONParsing(err) => Some(err),
-            Error::JSONStringify(_) | Error::NotImplemented => None,
-        }
-    }
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// Input at `path` is not valid JSON, `layer` counting how deeply stringified it was with 1
+    /// being the document itself
+    #[error("Error while parsing JSON at {path} (layer {layer}): {source}")]
+    JSONParsing {
+        path: JsonPath,
+        layer: usize,
+        #[source]
+        source: serde_json::Error,
+    },
+    /// Stringifying the value at the (.) separated key sequence `path` failed
+    #[error("Error while stringifying JSON at {path}: {reason}")]
+    JSONStringify { path: String, reason: String },
+    /// Paths given to stringify whose keys do not exist, in the order they were given
+    #[error("Paths not found: {}", .0.join(", "))]
+    MissingPaths(Vec<String>),
+    #[error("Error while reading {format}: {reason}")]
+    FormatRead { format: Format, reason: String },
+    /// The value at `path` cannot be represented in `format`, e.g. null in TOML
+    #[error("Cannot write {format} at {path}: {reason}")]
+    FormatWrite {
+        format: Format,
+        path: JsonPath,
+        reason: String,
+    },
+    #[error("Error while prettifying JSON: {0}")]
+    Prettify(#[source] serde_json::Error),
+    #[error("Invalid path: {0}")]
+    InvalidPath(String),
+    #[error("Invalid query: {0}")]
+    InvalidQuery(String),
+    #[error("Invalid filter: {0}")]
+    InvalidFilter(String),
+    #[error("Invalid schema: {0}")]
+    InvalidSchema(String),
+    /// A jq filter failed while running, e.g. indexing a number
+    #[error("Error while running filter: {0}")]
+    Filter(String),
+    #[error("Invalid option: {0}")]
+    InvalidOption(String),
+    /// A configuration file that cannot be read or holds unexpected values
+    #[error("Invalid config {}: {reason}", path.display())]
+    Config { path: PathBuf, reason: String },
+    /// A file pattern that is malformed or matches no files
+    #[error("Invalid glob: {0}")]
+    InvalidGlob(String),
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    /// Watching files for changes failed, e.g. when the inotify watch limit is reached
+    #[error("Error while watching files: {0}")]
+    Watch(String),
 }
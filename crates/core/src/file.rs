@@ -0,0 +1,266 @@
+use std::collections::BTreeSet;
+use std::ffi::OsString;
+use std::fs;
+use std::io::Write;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use notify_debouncer_full::new_debouncer;
+use notify_debouncer_full::notify::{self, RecursiveMode};
+use tempfile::NamedTempFile;
+
+use crate::error::Error;
+
+/// Files named by `patterns`, each an existing path or a glob such as `fixtures/**/*.json`, in
+/// the order given with duplicates removed
+pub fn expand_globs(patterns: &[String]) -> Result<Vec<PathBuf>, Error> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    for pattern in patterns {
+        let matches = match Path::new(pattern).is_file() {
+            true => vec![PathBuf::from(pattern)],
+            false => glob::glob(pattern)
+                .map_err(|err| Error::InvalidGlob(format!("{} - {}", err.msg, pattern)))?
+                .filter_map(|entry| entry.ok())
+                .filter(|path| path.is_file())
+                .collect(),
+        };
+        if matches.is_empty() {
+            return Err(Error::InvalidGlob(format!("No files match - {}", pattern)));
+        }
+
+        for path in matches {
+            if !files.contains(&path) {
+                files.push(path);
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+/// Deepest directory holding all of `files`, so their tree can be mirrored below another one
+pub fn common_dir(files: &[PathBuf]) -> PathBuf {
+    let mut dirs = files
+        .iter()
+        .map(|file| file.parent().unwrap_or(Path::new("")));
+    let Some(first) = dirs.next() else {
+        return PathBuf::new();
+    };
+
+    dirs.fold(first.to_path_buf(), |common, dir| {
+        common
+            .components()
+            .zip(dir.components())
+            .take_while(|(a, b)| a == b)
+            .map(|(a, _)| a)
+            .collect()
+    })
+}
+
+/// Where `file` goes when the tree under `base` is mirrored under `out_dir`
+pub fn mirrored_path(file: &Path, base: &Path, out_dir: &Path) -> PathBuf {
+    let relative = file.strip_prefix(base).unwrap_or(file);
+
+    out_dir.join(relative)
+}
+
+/// `<path>.bak`, e.g. `config.json.bak`
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(".bak");
+
+    PathBuf::from(name)
+}
+
+/// Replace the contents of `path` atomically by writing a temporary file next to it and renaming
+/// it over the original, so readers never see a partial file.
+///
+/// The original's permissions are kept, symlinks are followed so the link stays in place and with
+/// `backup` the original is copied to [`backup_path`] first.
+pub fn write_atomic(path: &Path, contents: &[u8], backup: bool) -> Result<(), Error> {
+    let path = fs::canonicalize(path)?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let permissions = fs::metadata(&path)?.permissions();
+
+    let mut temp = NamedTempFile::new_in(dir)?;
+    temp.write_all(contents)?;
+    temp.as_file().sync_all()?;
+    fs::set_permissions(temp.path(), permissions)?;
+
+    if backup {
+        fs::copy(&path, backup_path(&path))?;
+    }
+    temp.persist(&path).map_err(|err| err.error)?;
+
+    Ok(())
+}
+
+/// Call `on_change` with the files among `files` that changed, in the order given, until it
+/// breaks. Changes within `delay` of each other are reported together.
+///
+/// The directories holding the files are watched rather than the files themselves, so files
+/// replaced by editors saving atomically keep being watched.
+pub fn watch_files(
+    files: &[PathBuf],
+    delay: Duration,
+    mut on_change: impl FnMut(&[PathBuf]) -> ControlFlow<()>,
+) -> Result<(), Error> {
+    let watch_error = |errs: Vec<notify::Error>| {
+        let reasons: Vec<String> = errs.iter().map(|err| err.to_string()).collect();
+        Error::Watch(reasons.join(", "))
+    };
+
+    let canonicals = files
+        .iter()
+        .map(fs::canonicalize)
+        .collect::<Result<Vec<PathBuf>, _>>()?;
+    let dirs: BTreeSet<&Path> = canonicals.iter().filter_map(|file| file.parent()).collect();
+
+    let (tx, rx) = mpsc::channel();
+    let mut debouncer = new_debouncer(delay, None, tx).map_err(|err| watch_error(vec![err]))?;
+    for dir in dirs {
+        debouncer
+            .watch(dir, RecursiveMode::NonRecursive)
+            .map_err(|err| watch_error(vec![err]))?;
+    }
+
+    for events in rx {
+        // Reading the files is reported too, which would otherwise re-run on every run
+        let events = events.map_err(watch_error)?;
+        let paths: Vec<&PathBuf> = events
+            .iter()
+            .filter(|event| !event.kind.is_access())
+            .flat_map(|event| &event.paths)
+            .collect();
+        let changed: Vec<PathBuf> = files
+            .iter()
+            .zip(&canonicals)
+            .filter(|(_, canonical)| paths.contains(canonical))
+            .map(|(file, _)| file.clone())
+            .collect();
+
+        if !changed.is_empty() && on_change(&changed).is_break() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_globs() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("b")).unwrap();
+        for file in ["a.json", "b/c.json", "b/d.yaml"] {
+            fs::write(dir.path().join(file), "{}").unwrap();
+        }
+        let root = dir.path().display();
+        let patterns = vec![format!("{}/b/c.json", root), format!("{}/**/*.json", root)];
+
+        let actual = expand_globs(&patterns).unwrap();
+
+        let expected = vec![dir.path().join("b/c.json"), dir.path().join("a.json")];
+        assert_eq!(expected, actual);
+        assert!(matches!(
+            expand_globs(&[format!("{}/*.toml", root)]),
+            Err(Error::InvalidGlob(_))
+        ));
+    }
+
+    #[test]
+    fn test_mirrored_path() {
+        let files = vec![
+            PathBuf::from("fixtures/a/x.json"),
+            PathBuf::from("fixtures/b/c/y.json"),
+        ];
+
+        let base = common_dir(&files);
+
+        assert_eq!(PathBuf::from("fixtures"), base);
+        assert_eq!(
+            PathBuf::from("out/b/c/y.json"),
+            mirrored_path(&files[1], &base, Path::new("out"))
+        );
+    }
+
+    #[test]
+    fn test_write_atomic_with_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.json");
+        fs::write(&path, "{ \"a\": 1 }").unwrap();
+
+        write_atomic(&path, b"{\"a\":1}", true).unwrap();
+
+        assert_eq!("{\"a\":1}", fs::read_to_string(&path).unwrap());
+        assert_eq!(
+            "{ \"a\": 1 }",
+            fs::read_to_string(dir.path().join("data.json.bak")).unwrap()
+        );
+        assert_eq!(2, fs::read_dir(dir.path()).unwrap().count());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomic_keeps_permissions_and_symlinks() {
+        use std::os::unix::fs::{PermissionsExt, symlink};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.json");
+        let link = dir.path().join("link.json");
+        fs::write(&path, "{}").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        symlink(&path, &link).unwrap();
+
+        write_atomic(&link, b"[]", false).unwrap();
+
+        assert!(fs::symlink_metadata(&link).unwrap().is_symlink());
+        assert_eq!("[]", fs::read_to_string(&path).unwrap());
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(0o640, mode & 0o777);
+    }
+
+    #[test]
+    fn test_watch_files_reports_replaced_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.json");
+        let other = dir.path().join("other.json");
+        fs::write(&path, "{}").unwrap();
+
+        let writer = {
+            let (path, other) = (path.clone(), other.clone());
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(300));
+                fs::write(other, "[]").unwrap();
+                write_atomic(&path, b"[]", false).unwrap();
+            })
+        };
+        let mut actual = Vec::new();
+        watch_files(
+            std::slice::from_ref(&path),
+            Duration::from_millis(50),
+            |changed| {
+                actual.extend_from_slice(changed);
+                ControlFlow::Break(())
+            },
+        )
+        .unwrap();
+        writer.join().unwrap();
+
+        assert_eq!(vec![path], actual);
+    }
+
+    #[test]
+    fn test_write_atomic_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let actual = write_atomic(&dir.path().join("missing.json"), b"{}", false);
+
+        assert!(matches!(actual, Err(Error::Io(_))));
+    }
+}
@@ -0,0 +1,359 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde_json::{Map, Number, Value};
+
+use crate::binary::{
+    Encoding, decode_blob, encode_blob, read_bson, read_cbor, read_msgpack, write_bson, write_cbor,
+    write_msgpack,
+};
+use crate::error::Error;
+use crate::parse::{parse, parse_document, prettify};
+use crate::path::JsonPath;
+use crate::table::{Table, TableFormat, read_csv, write_table};
+use crate::xml::{read_xml, write_xml};
+
+/// Document format read by `--from` and written by `--to`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Json,
+    Yaml,
+    Toml,
+    /// Comma separated rows with a header, nested keys flattened into dotted columns
+    Csv,
+    /// Like [`Format::Csv`] with tabs
+    Tsv,
+    /// Elements mapped as described in [`crate::xml`]
+    Xml,
+    /// Binary formats decoded as described in [`crate::binary`]
+    Msgpack,
+    Cbor,
+    /// Object documents, several concatenated ones being read as an array
+    Bson,
+}
+
+impl Format {
+    pub fn is_binary(&self) -> bool {
+        matches!(self, Format::Msgpack | Format::Cbor | Format::Bson)
+    }
+
+    /// File extension used when converted files are written out, without the dot
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Yaml => "yaml",
+            Format::Toml => "toml",
+            Format::Csv => "csv",
+            Format::Tsv => "tsv",
+            Format::Xml => "xml",
+            Format::Msgpack => "msgpack",
+            Format::Cbor => "cbor",
+            Format::Bson => "bson",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Format::Json),
+            "yaml" | "yml" => Ok(Format::Yaml),
+            "toml" => Ok(Format::Toml),
+            "csv" => Ok(Format::Csv),
+            "tsv" => Ok(Format::Tsv),
+            "xml" => Ok(Format::Xml),
+            "msgpack" | "messagepack" => Ok(Format::Msgpack),
+            "cbor" => Ok(Format::Cbor),
+            "bson" => Ok(Format::Bson),
+            _ => Err(Error::InvalidOption(format!(
+                "Expected json, yaml, toml, csv, tsv, xml, msgpack, cbor or bson for format - {}",
+                s
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Json => write!(f, "JSON"),
+            Format::Yaml => write!(f, "YAML"),
+            Format::Toml => write!(f, "TOML"),
+            Format::Csv => write!(f, "CSV"),
+            Format::Tsv => write!(f, "TSV"),
+            Format::Xml => write!(f, "XML"),
+            Format::Msgpack => write!(f, "MessagePack"),
+            Format::Cbor => write!(f, "CBOR"),
+            Format::Bson => write!(f, "BSON"),
+        }
+    }
+}
+
+/// Read a document in a text `format`, unwrapping stringified JSON nested anywhere inside it,
+/// binary formats are read with [`read_bytes`]
+pub fn read(input: String, format: Format) -> Result<Value, Error> {
+    let read_error = |reason: String| Error::FormatRead { format, reason };
+
+    let val = match format {
+        Format::Json => return parse(input),
+        Format::Csv | Format::Tsv => return read_csv(&input, format),
+        Format::Xml => read_xml(&input)?,
+        Format::Msgpack | Format::Cbor | Format::Bson => return Err(binary_text(format)),
+        Format::Yaml => {
+            serde_yaml_ng::from_str(&input).map_err(|err| read_error(err.to_string()))?
+        }
+        Format::Toml => toml_to_json(
+            toml::from_str(&input).map_err(|err| read_error(err.to_string().trim().to_string()))?,
+        ),
+    };
+
+    parse_document(val)
+}
+
+/// Read a document in `format` from bytes, binary formats optionally given as hex or base64
+pub fn read_bytes(input: &[u8], format: Format, encoding: Encoding) -> Result<Value, Error> {
+    if !format.is_binary() {
+        if encoding != Encoding::Raw {
+            return Err(Error::InvalidOption(format!(
+                "Hex and base64 encodings only apply to binary formats, not {}",
+                format
+            )));
+        }
+        let input = String::from_utf8(input.to_vec()).map_err(|err| Error::FormatRead {
+            format,
+            reason: err.to_string(),
+        })?;
+        return read(input, format);
+    }
+
+    let input = decode_blob(input, encoding, format)?;
+    let val = match format {
+        Format::Msgpack => read_msgpack(&input)?,
+        Format::Cbor => read_cbor(&input)?,
+        _ => read_bson(&input)?,
+    };
+
+    parse_document(val)
+}
+
+fn binary_text(format: Format) -> Error {
+    Error::InvalidOption(format!(
+        "{} is a binary format, read and write it as bytes with read_bytes or write_bytes",
+        format
+    ))
+}
+
+/// TOML datetimes have no JSON counterpart, so they become strings
+fn toml_to_json(val: toml::Value) -> Value {
+    match val {
+        toml::Value::String(str) => Value::String(str),
+        toml::Value::Integer(int) => Value::from(int),
+        toml::Value::Float(float) => Number::from_f64(float).map_or(Value::Null, Value::Number),
+        toml::Value::Boolean(bool) => Value::Bool(bool),
+        toml::Value::Datetime(datetime) => Value::String(datetime.to_string()),
+        toml::Value::Array(arr) => Value::Array(arr.into_iter().map(toml_to_json).collect()),
+        toml::Value::Table(table) => Value::Object(Map::from_iter(
+            table.into_iter().map(|(k, v)| (k, toml_to_json(v))),
+        )),
+    }
+}
+
+/// Write a document in `format` as bytes, binary formats optionally as hex or base64 text
+pub fn write_bytes(
+    val: &Value,
+    format: Format,
+    pretty: bool,
+    encoding: Encoding,
+) -> Result<Vec<u8>, Error> {
+    let out = match format {
+        Format::Msgpack => write_msgpack(val)?,
+        Format::Cbor => write_cbor(val)?,
+        Format::Bson => write_bson(val)?,
+        format if encoding == Encoding::Raw => return Ok(write(val, format, pretty)?.into_bytes()),
+        format => {
+            return Err(Error::InvalidOption(format!(
+                "Hex and base64 encodings only apply to binary formats, not {}",
+                format
+            )));
+        }
+    };
+
+    Ok(encode_blob(out, encoding))
+}
+
+/// Write a document in a text `format`, `pretty` indenting JSON and XML as YAML, TOML and tables
+/// are always written one entry per line, binary formats are written with [`write_bytes`]
+pub fn write(val: &Value, format: Format, pretty: bool) -> Result<String, Error> {
+    let write_error = |path: &JsonPath, reason: String| Error::FormatWrite {
+        format,
+        path: path.clone(),
+        reason,
+    };
+
+    match format {
+        Format::Json if pretty => prettify(val.clone()),
+        Format::Json => Ok(val.to_string()),
+        Format::Yaml => serde_yaml_ng::to_string(val)
+            .map_err(|err| write_error(&JsonPath::default(), err.to_string())),
+        Format::Toml => {
+            if !val.is_object() {
+                return Err(write_error(
+                    &JsonPath::default(),
+                    String::from("a TOML document must be a table, wrap it in an object"),
+                ));
+            }
+            check_toml(val, &JsonPath::default())?;
+
+            toml::to_string_pretty(val)
+                .map_err(|err| write_error(&JsonPath::default(), err.to_string()))
+        }
+        Format::Xml => write_xml(val, pretty),
+        Format::Msgpack | Format::Cbor | Format::Bson => Err(binary_text(format)),
+        Format::Csv | Format::Tsv => {
+            let table_format = match format {
+                Format::Tsv => TableFormat::Tsv,
+                _ => TableFormat::Csv,
+            };
+            let mut out = Vec::new();
+            write_table(&mut out, &Table::from_value(val, None), table_format)?;
+
+            String::from_utf8(out).map_err(|err| write_error(&JsonPath::default(), err.to_string()))
+        }
+    }
+}
+
+/// TOML has no null and only 64-bit signed integers, mixed arrays are fine since TOML 1.0
+fn check_toml(val: &Value, path: &JsonPath) -> Result<(), Error> {
+    let unsupported = |reason: &str| Error::FormatWrite {
+        format: Format::Toml,
+        path: path.clone(),
+        reason: reason.to_string(),
+    };
+
+    match val {
+        Value::Null => Err(unsupported(
+            "TOML has no null, remove the key or give it a value",
+        )),
+        Value::Number(num) if num.is_u64() && num.as_i64().is_none() => Err(unsupported(
+            "integer does not fit in TOML's 64-bit signed integers",
+        )),
+        Value::Array(arr) => arr
+            .iter()
+            .enumerate()
+            .try_for_each(|(idx, val)| check_toml(val, &path.index(idx))),
+        Value::Object(obj) => obj
+            .iter()
+            .try_for_each(|(key, val)| check_toml(val, &path.key(key))),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_read_yaml_unwraps_nested_json() {
+        let input = String::from("name: app\nsettings: '{\"retries\": 3}'\nports: [80, 443]\n");
+
+        let actual = read(input, Format::Yaml).unwrap();
+
+        let expected = json!({"name": "app", "settings": {"retries": 3}, "ports": [80, 443]});
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_read_toml_datetime_as_string() {
+        let input = String::from("released = 1979-05-27T07:32:00Z\n[server]\nport = 80\n");
+
+        let actual = read(input, Format::Toml).unwrap();
+
+        let expected = json!({"released": "1979-05-27T07:32:00Z", "server": {"port": 80}});
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_write_yaml() {
+        let actual = write(&json!({"a": [1, {"b": "x"}]}), Format::Yaml, false).unwrap();
+
+        assert_eq!("a:\n- 1\n- b: x\n", actual);
+    }
+
+    #[test]
+    fn test_write_toml_mixed_array() {
+        let actual = write(&json!({"a": [1, "x"]}), Format::Toml, false).unwrap();
+
+        assert_eq!("a = [\n    1,\n    \"x\",\n]\n", actual);
+    }
+
+    #[test]
+    fn test_write_toml_null_reports_path() {
+        let actual = write(&json!({"a": [1, {"b": null}]}), Format::Toml, false);
+
+        match actual {
+            Err(Error::FormatWrite { path, .. }) => assert_eq!("a[1].b", path.to_string()),
+            other => panic!("Expected a TOML write error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_write_toml_root_must_be_table() {
+        let actual = write(&json!([1]), Format::Toml, false);
+
+        assert!(matches!(actual, Err(Error::FormatWrite { .. })));
+    }
+
+    #[test]
+    fn test_read_msgpack_hex_unwraps_nested_json() {
+        // {"a": "{\"b\":1}"}
+        let input = b"81 a1 61 a7 7b 22 62 22 3a 31 7d\n";
+
+        let actual = read_bytes(input, Format::Msgpack, Encoding::Hex).unwrap();
+
+        assert_eq!(json!({"a": {"b": 1}}), actual);
+    }
+
+    #[test]
+    fn test_write_cbor_base64_round_trip() {
+        let val = json!({"a": [1, "x"]});
+
+        let out = write_bytes(&val, Format::Cbor, false, Encoding::Base64).unwrap();
+        let actual = read_bytes(&out, Format::Cbor, Encoding::Base64).unwrap();
+
+        assert_eq!(val, actual);
+    }
+
+    #[test]
+    fn test_encoding_requires_binary_format() {
+        assert!(matches!(
+            read_bytes(b"{}", Format::Json, Encoding::Hex),
+            Err(Error::InvalidOption(_))
+        ));
+    }
+
+    #[test]
+    fn test_binary_formats_need_bytes() {
+        assert!(matches!(
+            read(String::from("{}"), Format::Msgpack),
+            Err(Error::InvalidOption(_))
+        ));
+        assert!(matches!(
+            write(&json!({}), Format::Cbor, false),
+            Err(Error::InvalidOption(_))
+        ));
+    }
+
+    #[test]
+    fn test_read_xml_unwraps_nested_json() {
+        let input = String::from("<resp><code>200</code><body>{\"ok\": true}</body></resp>");
+
+        let actual = read(input, Format::Xml).unwrap();
+
+        assert_eq!(json!({"resp": {"code": 200, "body": {"ok": true}}}), actual);
+    }
+}
@@ -0,0 +1,320 @@
+//! JSON Schema inference from sample documents.
+//!
+//! Samples are unwrapped before their shapes are merged, so the schema describes documents as
+//! `validate` sees them. Every stringified value is described as decoded and marked with the
+//! [`STRINGIFIED`] annotation, e.g. `{"type": "object", "x-stringified": true, ...}`, which
+//! validators ignore. Those decoding to strings are also marked with
+//! `"contentMediaType": "application/json"`, which JSON Schema defines for strings only.
+
+use std::collections::{BTreeSet, HashSet};
+
+use serde_json::{Map, Value, json};
+
+use crate::error::Error;
+use crate::parse::parse_tracked;
+use crate::path::JsonPath;
+
+pub const DRAFT_2020_12: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Annotation keyword marking values that were delivered as stringified JSON
+pub const STRINGIFIED: &str = "x-stringified";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Type {
+    Null,
+    Boolean,
+    Integer,
+    Number,
+    String,
+    Array,
+    Object,
+}
+
+impl Type {
+    fn of(val: &Value) -> Self {
+        match val {
+            Value::Null => Type::Null,
+            Value::Bool(_) => Type::Boolean,
+            Value::Number(num) if num.is_f64() => Type::Number,
+            Value::Number(_) => Type::Integer,
+            Value::String(_) => Type::String,
+            Value::Array(_) => Type::Array,
+            Value::Object(_) => Type::Object,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Type::Null => "null",
+            Type::Boolean => "boolean",
+            Type::Integer => "integer",
+            Type::Number => "number",
+            Type::String => "string",
+            Type::Array => "array",
+            Type::Object => "object",
+        }
+    }
+}
+
+/// Everything seen at one location across the samples
+#[derive(Debug, Default)]
+struct Shape {
+    /// Number of values seen, for a property the number of objects holding it
+    count: usize,
+    types: BTreeSet<Type>,
+    /// Properties of the objects seen, in the order they were first seen
+    properties: Vec<(String, Shape)>,
+    objects: usize,
+    /// Elements of all the arrays seen
+    items: Option<Box<Shape>>,
+    stringified: bool,
+}
+
+impl Shape {
+    fn add(&mut self, val: &Value, path: &JsonPath, stringified: &HashSet<&JsonPath>) {
+        self.count += 1;
+        self.types.insert(Type::of(val));
+        self.stringified |= stringified.contains(path);
+
+        match val {
+            Value::Array(arr) => {
+                let items = self.items.get_or_insert_default();
+                for (idx, item) in arr.iter().enumerate() {
+                    items.add(item, &path.index(idx), stringified);
+                }
+            }
+            Value::Object(obj) => {
+                self.objects += 1;
+                for (key, item) in obj {
+                    let idx = match self.properties.iter().position(|(k, _)| k == key) {
+                        Some(idx) => idx,
+                        None => {
+                            self.properties.push((key.clone(), Shape::default()));
+                            self.properties.len() - 1
+                        }
+                    };
+                    self.properties[idx]
+                        .1
+                        .add(item, &path.key(key), stringified);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn schema(&self) -> Map<String, Value> {
+        let mut schema = Map::new();
+
+        // Integers are numbers too, so a mix of both is a number
+        let types: Vec<&str> = self
+            .types
+            .iter()
+            .filter(|ty| !(**ty == Type::Integer && self.types.contains(&Type::Number)))
+            .map(|ty| ty.name())
+            .collect();
+        match types.as_slice() {
+            [] => {}
+            [ty] => {
+                schema.insert(String::from("type"), json!(ty));
+            }
+            types => {
+                schema.insert(String::from("type"), json!(types));
+            }
+        }
+        if self.stringified {
+            schema.insert(String::from(STRINGIFIED), json!(true));
+            if self.types.contains(&Type::String) {
+                schema.insert(String::from("contentMediaType"), json!("application/json"));
+            }
+        }
+
+        if !self.properties.is_empty() {
+            let properties = self
+                .properties
+                .iter()
+                .map(|(key, shape)| (key.clone(), Value::Object(shape.schema())))
+                .collect();
+            let required: Vec<&str> = self
+                .properties
+                .iter()
+                .filter(|(_, shape)| shape.count == self.objects)
+                .map(|(key, _)| key.as_str())
+                .collect();
+
+            schema.insert(String::from("properties"), Value::Object(properties));
+            if !required.is_empty() {
+                schema.insert(String::from("required"), json!(required));
+            }
+        }
+        if let Some(items) = self.items.as_ref().filter(|items| items.count > 0) {
+            schema.insert(String::from("items"), Value::Object(items.schema()));
+        }
+
+        schema
+    }
+}
+
+/// Draft 2020-12 schema covering every sample added, a property being required when all the
+/// objects at its location hold it
+#[derive(Debug, Default)]
+pub struct Inference {
+    root: Shape,
+}
+
+impl Inference {
+    pub fn new() -> Self {
+        Inference::default()
+    }
+
+    /// Add an unwrapped sample, `stringified` being the paths of values decoded from strings as
+    /// returned by [`parse_tracked`]
+    pub fn add_value(&mut self, val: &Value, stringified: &[JsonPath]) {
+        let stringified: HashSet<&JsonPath> = stringified.iter().collect();
+
+        self.root.add(val, &JsonPath::default(), &stringified);
+    }
+
+    /// Unwrap a JSON document and add it as a sample
+    pub fn add(&mut self, input: String) -> Result<(), Error> {
+        let (val, decoded) = parse_tracked(input)?;
+        self.add_value(&val, &decoded);
+
+        Ok(())
+    }
+
+    /// Add every non-blank line of JSON Lines input as a sample
+    pub fn add_lines(&mut self, input: &str) -> Result<(), Error> {
+        input
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .try_for_each(|line| self.add(line.to_string()))
+    }
+
+    pub fn schema(&self) -> Value {
+        let mut schema = Map::new();
+        schema.insert(String::from("$schema"), json!(DRAFT_2020_12));
+        schema.extend(self.root.schema());
+
+        Value::Object(schema)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validate::Schema;
+
+    fn infer(samples: &[Value]) -> Value {
+        let mut inference = Inference::new();
+        for sample in samples {
+            inference.add_value(sample, &[]);
+        }
+
+        inference.schema()
+    }
+
+    #[test]
+    fn test_infer_merges_types() {
+        let actual = infer(&[json!({"a": 1, "b": "x"}), json!({"a": 1.5, "b": null})]);
+
+        let expected = json!({
+            "$schema": DRAFT_2020_12,
+            "type": "object",
+            "properties": {
+                "a": {"type": "number"},
+                "b": {"type": ["null", "string"]},
+            },
+            "required": ["a", "b"],
+        });
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_infer_required_fields() {
+        let actual = infer(&[json!([{"id": 1, "tag": "a"}, {"id": 2}]), json!([])]);
+
+        let expected = json!({
+            "$schema": DRAFT_2020_12,
+            "type": "array",
+            "items": {
+                "type": "object",
+                "properties": {
+                    "id": {"type": "integer"},
+                    "tag": {"type": "string"},
+                },
+                "required": ["id"],
+            },
+        });
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_infer_lines_with_stringified_fields() {
+        let mut inference = Inference::new();
+
+        inference
+            .add_lines(concat!(
+                "{\"id\": 1, \"body\": \"[1, 2]\", \"meta\": \"{}\"}\n\n",
+                "{\"id\": 2, \"body\": \"{}\"}\n",
+            ))
+            .unwrap();
+
+        let actual = inference.schema();
+        let expected = json!({
+            "type": ["array", "object"],
+            "x-stringified": true,
+            "items": {"type": "integer"},
+        });
+        assert_eq!(expected, actual["properties"]["body"]);
+        assert_eq!(
+            json!({"type": "object", "x-stringified": true}),
+            actual["properties"]["meta"]
+        );
+        assert_eq!(json!(["id", "body"]), actual["required"]);
+
+        let schema = Schema::new(&actual).unwrap();
+        assert!(schema.validate(&json!({"id": 3, "body": [4]})).is_empty());
+    }
+
+    #[test]
+    fn test_infer_marks_stringified_strings_with_media_type() {
+        let mut inference = Inference::new();
+        let root = JsonPath::default();
+
+        inference.add_value(
+            &json!({"name": "x", "body": {"n": 1}}),
+            &[root.key("name"), root.key("body")],
+        );
+
+        let actual = inference.schema();
+        let expected = json!({
+            "name": {
+                "type": "string",
+                "x-stringified": true,
+                "contentMediaType": "application/json",
+            },
+            "body": {
+                "type": "object",
+                "x-stringified": true,
+                "properties": {"n": {"type": "integer"}},
+                "required": ["n"],
+            },
+        });
+        assert_eq!(expected, actual["properties"]);
+    }
+
+    #[test]
+    fn test_inferred_schema_validates_samples() {
+        let samples = [
+            json!({"id": 1, "items": [{"sku": "a", "qty": 2}], "note": null}),
+            json!({"id": 2, "items": [], "note": "gift"}),
+        ];
+
+        let schema = Schema::new(&infer(&samples)).unwrap();
+
+        for sample in &samples {
+            assert!(schema.validate(sample).is_empty());
+        }
+        assert!(!schema.validate(&json!({"id": "3", "items": []})).is_empty());
+    }
+}
@@ -0,0 +1,191 @@
+use std::str::FromStr;
+
+use jaq_core::load::parse::{BinaryOp, Term};
+use jaq_core::load::{self, Arena, File, Loader};
+use jaq_core::{Compiler, Ctx, Native, RcIter};
+use jaq_json::Val;
+use serde_json::Value;
+
+use crate::error::Error;
+
+/// Compiled jq filter with the jq standard library available, e.g. `.items | map(.name)`
+pub struct Filter {
+    filter: jaq_core::Filter<Native<Val>>,
+    keeps_structure: bool,
+}
+
+impl Filter {
+    /// Whether outputs keep the structure of the input, i.e. the filter is built from `.` and
+    /// update assignments such as `.a |= f` or `.b = 1` only
+    pub fn keeps_structure(&self) -> bool {
+        self.keeps_structure
+    }
+
+    /// All values the filter yields for `input`, stopping at the first error
+    pub fn run(&self, input: Value) -> Result<Vec<Value>, Error> {
+        let inputs = RcIter::new(std::iter::empty());
+
+        self.filter
+            .run((Ctx::new([], &inputs), Val::from(input)))
+            .map(|result| {
+                result
+                    .map(Value::from)
+                    .map_err(|err| Error::Filter(err.to_string()))
+            })
+            .collect()
+    }
+}
+
+fn keeps_structure(term: &Term<&str>) -> bool {
+    match term {
+        Term::Id => true,
+        Term::BinOp(_, op, _) => matches!(
+            op,
+            BinaryOp::Assign | BinaryOp::Update | BinaryOp::UpdateMath(_) | BinaryOp::UpdateAlt
+        ),
+        Term::Pipe(l, None, r) => keeps_structure(l) && keeps_structure(r),
+        _ => false,
+    }
+}
+
+fn load_error(err: load::Error<&str>) -> String {
+    match err {
+        load::Error::Io(errs) => errs
+            .into_iter()
+            .map(|(path, msg)| format!("could not load {} - {}", path, msg))
+            .collect::<Vec<_>>()
+            .join(", "),
+        load::Error::Lex(errs) => errs
+            .into_iter()
+            .map(|(expect, at)| format!("expected {} at `{}`", expect.as_str(), at))
+            .collect::<Vec<_>>()
+            .join(", "),
+        load::Error::Parse(errs) => errs
+            .into_iter()
+            .map(|(expect, at)| format!("expected {} at `{}`", expect.as_str(), at))
+            .collect::<Vec<_>>()
+            .join(", "),
+    }
+}
+
+impl FromStr for Filter {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: String| Error::InvalidFilter(format!("{} - {}", reason, s));
+
+        let program = File { code: s, path: () };
+        let loader = Loader::new(jaq_std::defs().chain(jaq_json::defs()));
+        let arena = Arena::default();
+
+        let modules = loader.load(&arena, program).map_err(|errs| {
+            invalid(
+                errs.into_iter()
+                    .map(|(_, err)| load_error(err))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            )
+        })?;
+
+        Compiler::default()
+            .with_funs(jaq_std::funs().chain(jaq_json::funs()))
+            .compile(modules)
+            .map(|filter| Filter {
+                filter,
+                keeps_structure: load::parse(s, |p| p.term()).is_some_and(|t| keeps_structure(&t)),
+            })
+            .map_err(|errs| {
+                invalid(
+                    errs.into_iter()
+                        .flat_map(|(_, errs)| errs)
+                        .map(|(name, undefined)| {
+                            format!("undefined {} {}", undefined.as_str(), name)
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::parse::parse_tracked;
+    use crate::stringify::restringify;
+
+    #[test]
+    fn test_filter_run() {
+        let filter: Filter = ".items | map(select(.price < 10) | .name)".parse().unwrap();
+        let input = json!({"items": [{"name": "a", "price": 5}, {"name": "b", "price": 15}]});
+
+        let actual = filter.run(input).unwrap();
+
+        assert_eq!(vec![json!(["a"])], actual);
+    }
+
+    #[test]
+    fn test_filter_multiple_outputs() {
+        let filter: Filter = ".[] | . * 2".parse().unwrap();
+
+        let actual = filter.run(json!([1, 2])).unwrap();
+
+        assert_eq!(vec![json!(2), json!(4)], actual);
+    }
+
+    #[test]
+    fn test_invalid_filter() {
+        assert!(matches!(
+            ".[".parse::<Filter>(),
+            Err(Error::InvalidFilter(_))
+        ));
+        assert!(matches!(
+            "nonexistent_fn".parse::<Filter>(),
+            Err(Error::InvalidFilter(_))
+        ));
+    }
+
+    #[test]
+    fn test_filter_keeps_structure() {
+        for filter in [".", ".a |= tostring", ".x = 1 | .y += 2", ".a //= ."] {
+            assert!(
+                filter.parse::<Filter>().unwrap().keeps_structure(),
+                "{}",
+                filter
+            );
+        }
+        for filter in [".items[0]", "map(.)", "{a: .x}", ".a, .", ".a |= . | .b"] {
+            assert!(
+                !filter.parse::<Filter>().unwrap().keeps_structure(),
+                "{}",
+                filter
+            );
+        }
+    }
+
+    #[test]
+    fn test_projection_moving_stringified_field() {
+        let (input, decoded) = parse_tracked(String::from(r#"{"a": "[1, 2]", "b": 1}"#)).unwrap();
+
+        // Restringifying `{a: .b, c: .a}` would turn `.a` into "1" and leave `.c` an array
+        let projection: Filter = "{a: .b, c: .a}".parse().unwrap();
+        assert!(!projection.keeps_structure());
+
+        let update: Filter = ".b |= . + 1".parse().unwrap();
+        assert!(update.keeps_structure());
+        let mut actual = update.run(input).unwrap();
+        restringify(&mut actual[0], &decoded);
+        assert_eq!(vec![json!({"a": "[1,2]", "b": 2})], actual);
+    }
+
+    #[test]
+    fn test_filter_runtime_error() {
+        let filter: Filter = ".a".parse().unwrap();
+
+        let actual = filter.run(json!([1]));
+
+        assert!(matches!(actual, Err(Error::Filter(_))));
+    }
+}
@@ -1,5 +1,18 @@
+pub mod binary;
 pub mod compare;
-mod error;
+pub mod config;
+pub mod error;
+pub mod file;
+pub mod format;
+pub mod infer;
+pub mod jq;
+mod markdown;
+pub mod merge;
 pub mod parse;
+pub mod path;
+pub mod query;
 pub mod remove_spaces;
 pub mod stringify;
+pub mod table;
+pub mod validate;
+pub mod xml;
@@ -0,0 +1,46 @@
+//! Escaping for GitHub-flavoured Markdown tables, shared by table exports and diff reports.
+
+/// Escape `value` for a table cell, where a pipe would end the cell and a line break the row.
+///
+/// Pipes are escaped even inside code spans, as cells are split before code spans are parsed.
+pub(crate) fn escape_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace(['\n', '\r'], " ")
+}
+
+/// Backtick run one longer than any inside `content`, so it can be fenced or code spanned
+pub(crate) fn backtick_fence(content: &str, min: usize) -> String {
+    let mut longest = 0;
+    let mut current = 0;
+    for ch in content.chars() {
+        current = if ch == '`' { current + 1 } else { 0 };
+        longest = longest.max(current);
+    }
+
+    "`".repeat(min.max(longest + 1))
+}
+
+/// `value` as a code span fit for a table cell, empty for an empty value
+pub(crate) fn code_span(value: &str) -> String {
+    if value.is_empty() {
+        return String::new();
+    }
+    let fence = backtick_fence(value, 1);
+
+    format!("{} {} {}", fence, escape_cell(value), fence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_cell() {
+        assert_eq!("a\\|b c", escape_cell("a|b\nc"));
+    }
+
+    #[test]
+    fn test_code_span() {
+        assert_eq!("`` a`\\|b ``", code_span("a`|b"));
+        assert_eq!("", code_span(""));
+    }
+}
@@ -0,0 +1,191 @@
+use std::fmt;
+
+use serde_json::{Map, Value};
+
+use crate::path::JsonPath;
+
+/// A path changed differently on both sides, `None` meaning the value is absent on that side
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub path: JsonPath,
+    pub base: Option<Value>,
+    pub ours: Option<Value>,
+    pub theirs: Option<Value>,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |val: &Option<Value>| match val {
+            Some(val) => val.to_string(),
+            None => String::from("<missing>"),
+        };
+
+        write!(
+            f,
+            "{}: base {}, ours {}, theirs {}",
+            self.path,
+            show(&self.base),
+            show(&self.ours),
+            show(&self.theirs)
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeResult {
+    /// Merged document, holding our value wherever there is a conflict
+    pub merged: Value,
+    pub conflicts: Vec<Conflict>,
+}
+
+/// Three-way merge of two documents derived from `base`.
+///
+/// Objects are merged key by key so changes to different paths combine, arrays and scalars are
+/// replaced as a whole and conflict when both sides changed them differently.
+pub fn merge3(base: &Value, ours: &Value, theirs: &Value) -> MergeResult {
+    let mut conflicts = Vec::new();
+    let merged = merge_value(
+        &JsonPath::default(),
+        Some(base),
+        Some(ours),
+        Some(theirs),
+        &mut conflicts,
+    )
+    .unwrap_or(Value::Null);
+
+    MergeResult { merged, conflicts }
+}
+
+fn merge_value(
+    path: &JsonPath,
+    base: Option<&Value>,
+    ours: Option<&Value>,
+    theirs: Option<&Value>,
+    conflicts: &mut Vec<Conflict>,
+) -> Option<Value> {
+    if ours == theirs || theirs == base {
+        return ours.cloned();
+    }
+    if ours == base {
+        return theirs.cloned();
+    }
+
+    let empty = Map::new();
+    match (base, ours, theirs) {
+        (
+            None | Some(Value::Object(_)),
+            Some(Value::Object(ours_obj)),
+            Some(Value::Object(theirs_obj)),
+        ) => {
+            let base_obj = match base {
+                Some(Value::Object(obj)) => obj,
+                _ => &empty,
+            };
+
+            // Keep our key order, followed by keys only they have
+            let keys =
+                ours_obj
+                    .keys()
+                    .chain(theirs_obj.keys().filter(|key| !ours_obj.contains_key(*key)))
+                    .chain(base_obj.keys().filter(|key| {
+                        !ours_obj.contains_key(*key) && !theirs_obj.contains_key(*key)
+                    }));
+
+            let mut merged = Map::new();
+            for key in keys {
+                let child = merge_value(
+                    &path.key(key),
+                    base_obj.get(key),
+                    ours_obj.get(key),
+                    theirs_obj.get(key),
+                    conflicts,
+                );
+                if let Some(val) = child {
+                    merged.insert(key.clone(), val);
+                }
+            }
+
+            Some(Value::Object(merged))
+        }
+        _ => {
+            conflicts.push(Conflict {
+                path: path.clone(),
+                base: base.cloned(),
+                ours: ours.cloned(),
+                theirs: theirs.cloned(),
+            });
+
+            ours.cloned()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_merge_non_overlapping_changes() {
+        let base = json!({"a": 1, "b": {"c": 1, "d": 1}, "e": 1});
+        let ours = json!({"a": 2, "b": {"c": 1, "d": 1}, "e": 1});
+        let theirs = json!({"a": 1, "b": {"c": 1, "d": 2}, "f": 1});
+
+        let actual = merge3(&base, &ours, &theirs);
+
+        assert!(actual.conflicts.is_empty());
+        assert_eq!(
+            json!({"a": 2, "b": {"c": 1, "d": 2}, "f": 1}),
+            actual.merged
+        );
+    }
+
+    #[test]
+    fn test_merge_identical_changes() {
+        let base = json!({"a": [1]});
+        let changed = json!({"a": [1, 2]});
+
+        let actual = merge3(&base, &changed, &changed);
+
+        assert!(actual.conflicts.is_empty());
+        assert_eq!(changed, actual.merged);
+    }
+
+    #[test]
+    fn test_merge_reports_conflicts() {
+        let base = json!({"a": 1, "b": 1, "c": [1]});
+        let ours = json!({"a": 2, "c": [1, 2]});
+        let theirs = json!({"a": 3, "b": 2, "c": [0]});
+
+        let actual = merge3(&base, &ours, &theirs);
+
+        assert_eq!(json!({"a": 2, "c": [1, 2]}), actual.merged);
+        assert_eq!(3, actual.conflicts.len());
+        assert_eq!(
+            Conflict {
+                path: JsonPath::default().key("b"),
+                base: Some(json!(1)),
+                ours: None,
+                theirs: Some(json!(2)),
+            },
+            actual.conflicts[2]
+        );
+        assert_eq!(
+            "b: base 1, ours <missing>, theirs 2",
+            actual.conflicts[2].to_string()
+        );
+    }
+
+    #[test]
+    fn test_merge_keys_added_on_both_sides() {
+        let base = json!({});
+        let ours = json!({"new": {"x": 1}});
+        let theirs = json!({"new": {"y": 1}});
+
+        let actual = merge3(&base, &ours, &theirs);
+
+        assert!(actual.conflicts.is_empty());
+        assert_eq!(json!({"new": {"x": 1, "y": 1}}), actual.merged);
+    }
+}
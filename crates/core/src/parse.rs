@@ -1,22 +1,62 @@
-use std::cell::OnceCell;
+use std::sync::LazyLock;
 
 use fancy_regex::{Captures, Regex};
+use serde::Serialize;
+use serde_json::ser::{PrettyFormatter, Serializer};
 use serde_json::{Map, Value};
 
 use crate::error::Error;
+use crate::path::JsonPath;
 
-const TRAILING_QUOTE_REGEX: OnceCell<Regex> = OnceCell::new();
-const UNESCAPE_REGEX: OnceCell<Regex> = OnceCell::new();
+static TRAILING_QUOTE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(^")|("$)"#).unwrap());
+static UNESCAPE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?<!\\)((?:\\\\)*)\\""#).unwrap());
 
 pub fn parse(input: String) -> Result<Value, Error> {
-    parse_value(Value::String(input.clone()))
+    parse_value(
+        Value::String(input.clone()),
+        &JsonPath::default(),
+        0,
+        false,
+        &mut Vec::new(),
+    )
 }
 
-fn parse_value(val: Value) -> Result<Value, Error> {
-    let binding = TRAILING_QUOTE_REGEX;
-    let trailing_regex = binding.get_or_init(|| Regex::new(r#"(^")|("$)"#).unwrap());
-    let binding = UNESCAPE_REGEX;
-    let unescape_regex = binding.get_or_init(|| Regex::new(r#"(?<!\\)((?:\\\\)*)\\""#).unwrap());
+/// Parse like [`parse`], also returning the paths of nested values that were decoded from
+/// strings, once for every layer of stringification, so they can be stringified back
+pub fn parse_tracked(input: String) -> Result<(Value, Vec<JsonPath>), Error> {
+    let mut decoded = Vec::new();
+    let val = parse_value(
+        Value::String(input.clone()),
+        &JsonPath::default(),
+        0,
+        false,
+        &mut decoded,
+    )?;
+
+    Ok((val, decoded))
+}
+
+/// Unwrap stringified JSON nested in an already decoded document, e.g. one read from YAML.
+///
+/// Unlike [`parse`], strings that fail to parse are kept as text unless they look like an object
+/// or array, as scalars in these formats are mostly plain text such as `12 apples` or `1.2.3`
+pub fn parse_document(val: Value) -> Result<Value, Error> {
+    parse_value(val, &JsonPath::default(), 1, true, &mut Vec::new())
+}
+
+/// `layer` is the number of times the value at `path` has already been decoded from a string,
+/// `lenient` keeps strings that are not JSON objects or arrays as text, see [`parse_document`]
+fn parse_value(
+    val: Value,
+    path: &JsonPath,
+    layer: usize,
+    lenient: bool,
+    decoded: &mut Vec<JsonPath>,
+) -> Result<Value, Error> {
+    let trailing_regex = &*TRAILING_QUOTE_REGEX;
+    let unescape_regex = &*UNESCAPE_REGEX;
 
     match val {
         Value::String(val) => {
@@ -32,21 +72,39 @@ fn parse_value(val: Value) -> Result<Value, Error> {
                 .to_string();
 
             match serde_json::from_str::<Value>(&replaced) {
-                Ok(parsed) => parse_value(parsed),
+                Ok(parsed) => {
+                    if !path.0.is_empty() {
+                        decoded.push(path.clone());
+                    }
+                    parse_value(parsed, path, layer + 1, lenient, decoded)
+                }
                 Err(err) => {
                     // Error struct does not expose this specfic error
-                    match err.to_string().contains("expected value") {
+                    let is_text = err.to_string().contains("expected value")
+                        || (lenient && !replaced.trim_start().starts_with(['{', '[']));
+                    match is_text {
                         true => Ok(Value::String(replaced)),
-                        _ => Err(Error::JSONParsing(err)),
+                        _ => Err(Error::JSONParsing {
+                            path: path.clone(),
+                            layer: layer + 1,
+                            source: err,
+                        }),
                     }
                 }
             }
         }
-        Value::Array(arr) => arr.iter().map(|x| parse_value(x.clone())).collect(),
+        Value::Array(arr) => arr
+            .iter()
+            .enumerate()
+            .map(|(idx, x)| parse_value(x.clone(), &path.index(idx), layer, lenient, decoded))
+            .collect(),
         Value::Object(obj) => {
             let iter_map: Vec<(String, Value)> = obj
                 .iter()
-                .map(|(k, v)| parse_value(v.clone()).map(|val| (k.clone(), val)))
+                .map(|(k, v)| {
+                    parse_value(v.clone(), &path.key(k), layer, lenient, decoded)
+                        .map(|val| (k.clone(), val))
+                })
                 .collect::<Result<Vec<(String, Value)>, Error>>()?;
             Ok(Value::Object(Map::from_iter(iter_map)))
         }
@@ -55,7 +113,29 @@ fn parse_value(val: Value) -> Result<Value, Error> {
 }
 
 pub fn prettify(val: Value) -> Result<String, Error> {
-    serde_json::to_string_pretty(&val).map_err(|_| Error::NotImplemented)
+    serde_json::to_string_pretty(&val).map_err(Error::Prettify)
+}
+
+/// Reformat JSON text as is, leaving stringified values alone, indented by `indent` spaces or
+/// compact without it
+pub fn reformat(input: &str, indent: Option<usize>) -> Result<String, Error> {
+    let val: Value = serde_json::from_str(input).map_err(|err| Error::JSONParsing {
+        path: JsonPath::default(),
+        layer: 1,
+        source: err,
+    })?;
+
+    let Some(indent) = indent else {
+        return Ok(val.to_string());
+    };
+
+    let indent = " ".repeat(indent);
+    let formatter = PrettyFormatter::with_indent(indent.as_bytes());
+    let mut out = Vec::new();
+    val.serialize(&mut Serializer::with_formatter(&mut out, formatter))
+        .map_err(Error::Prettify)?;
+
+    Ok(String::from_utf8_lossy(&out).to_string())
 }
 
 #[cfg(test)]
@@ -154,6 +234,67 @@ mod tests {
         let actual = parse(sample);
 
         println!("{:?}", actual);
-        assert_eq!(true, actual.is_err());
+        assert!(actual.is_err());
+    }
+
+    #[test]
+    fn test_parse_error_reports_path_and_layer() {
+        let sample = String::from(r#"{"a": [1, "[1,"]}"#);
+        let actual = parse(sample);
+
+        match actual {
+            Err(Error::JSONParsing { path, layer, .. }) => {
+                assert_eq!("a[1]", path.to_string());
+                assert_eq!(2, layer);
+            }
+            other => panic!("Expected a parsing error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_tracked_records_decoded_paths() {
+        let sample = String::from(r#"{"a": "[1, 2]", "b": {"c": "{}"}, "d": "hello"}"#);
+        let (actual, decoded) = parse_tracked(sample).unwrap();
+
+        assert_eq!(json!({"a": [1, 2], "b": {"c": {}}, "d": "hello"}), actual);
+        assert_eq!(
+            vec![
+                JsonPath::default().key("a"),
+                JsonPath::default().key("b").key("c")
+            ],
+            decoded
+        );
+    }
+
+    #[test]
+    fn test_parse_document_keeps_nested_plain_text() {
+        let sample = json!({"date": "1979-05-27", "text": "12 apples", "list": "[1, 2]"});
+        let actual = parse_document(sample);
+
+        let expected = json!({"date": "1979-05-27", "text": "12 apples", "list": [1, 2]});
+
+        assert_eq!(expected, actual.unwrap());
+        assert!(parse_document(json!({"a": "[1,"})).is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_nested_invalid_json() {
+        for sample in [r#"{"a": "12 apples"}"#, r#"{"a": "1.2.3"}"#] {
+            assert!(matches!(
+                parse(String::from(sample)),
+                Err(Error::JSONParsing { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_reformat_keeps_stringified_values() {
+        let input = "{ \"a\": \"{\\\"b\\\": 1}\",\n  \"c\": [1, 2] }";
+
+        let actual = reformat(input, None).unwrap();
+
+        assert_eq!("{\"a\":\"{\\\"b\\\": 1}\",\"c\":[1,2]}", actual);
+        assert_eq!("[\n    1\n]", reformat("[1]", Some(4)).unwrap());
+        assert!(reformat("{", Some(2)).is_err());
     }
 }
@@ -0,0 +1,230 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+
+use crate::error::Error;
+
+/// A single step from a JSON value into one of its children
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// Location of a value inside a JSON document, rendered as `a.b[0].c`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct JsonPath(pub Vec<PathSegment>);
+
+impl JsonPath {
+    pub fn child(&self, segment: PathSegment) -> JsonPath {
+        let mut segments = self.0.clone();
+        segments.push(segment);
+
+        JsonPath(segments)
+    }
+
+    pub fn key(&self, key: &str) -> JsonPath {
+        self.child(PathSegment::Key(key.to_string()))
+    }
+
+    pub fn index(&self, idx: usize) -> JsonPath {
+        self.child(PathSegment::Index(idx))
+    }
+
+    /// Value at this path inside `val`, if every segment exists
+    pub fn get_mut<'a>(&self, val: &'a mut Value) -> Option<&'a mut Value> {
+        self.0.iter().try_fold(val, |val, segment| match segment {
+            PathSegment::Key(key) => val.get_mut(key.as_str()),
+            PathSegment::Index(idx) => val.get_mut(*idx),
+        })
+    }
+}
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "$");
+        }
+        for (idx, segment) in self.0.iter().enumerate() {
+            match segment {
+                PathSegment::Key(key) if idx == 0 => write!(f, "{}", key)?,
+                PathSegment::Key(key) => write!(f, ".{}", key)?,
+                PathSegment::Index(i) => write!(f, "[{}]", i)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for JsonPath {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PatternSegment {
+    Key(String),
+    Index(usize),
+    /// `*` - any single object key or array index
+    Any,
+    /// `[*]` - any single array index
+    AnyIndex,
+    /// `**` - zero or more segments
+    AnyDepth,
+}
+
+/// Pattern matched against [`JsonPath`]s, anchored at the document root.
+///
+/// Segments are separated by (.) like stringify paths; `[n]` and `[*]` step into
+/// arrays, `*` matches any single key or index and `**` matches any depth, e.g.
+/// `items[*].id`, `*.timestamp` or `**.traceId`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathPattern {
+    source: String,
+    segments: Vec<PatternSegment>,
+}
+
+impl PathPattern {
+    pub fn matches(&self, path: &JsonPath) -> bool {
+        matches_segments(&self.segments, &path.0)
+    }
+}
+
+fn matches_segments(pattern: &[PatternSegment], path: &[PathSegment]) -> bool {
+    match (pattern.first(), path.first()) {
+        (None, None) => true,
+        (Some(PatternSegment::AnyDepth), _) => {
+            matches_segments(&pattern[1..], path)
+                || (!path.is_empty() && matches_segments(pattern, &path[1..]))
+        }
+        (None, Some(_)) | (Some(_), None) => false,
+        (Some(expected), Some(actual)) => {
+            let is_match = match (expected, actual) {
+                (PatternSegment::Any, _) => true,
+                (PatternSegment::AnyIndex, PathSegment::Index(_)) => true,
+                (PatternSegment::Key(a), PathSegment::Key(b)) => a == b,
+                (PatternSegment::Index(a), PathSegment::Index(b)) => a == b,
+                _ => false,
+            };
+            is_match && matches_segments(&pattern[1..], &path[1..])
+        }
+    }
+}
+
+impl FromStr for PathPattern {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| Error::InvalidPath(format!("{} - {}", reason, s));
+
+        if s.is_empty() {
+            return Err(invalid("Empty path"));
+        }
+
+        let mut segments = Vec::new();
+        for part in s.split('.') {
+            let (key, mut rest) = match part.find('[') {
+                Some(idx) => part.split_at(idx),
+                None => (part, ""),
+            };
+
+            match key {
+                "" if rest.is_empty() => return Err(invalid("Empty segment")),
+                "" => {}
+                "*" => segments.push(PatternSegment::Any),
+                "**" => segments.push(PatternSegment::AnyDepth),
+                key => segments.push(PatternSegment::Key(key.to_string())),
+            }
+
+            while !rest.is_empty() {
+                let end = rest.find(']').ok_or_else(|| invalid("Unclosed ["))?;
+                let index = &rest[1..end];
+                segments.push(match index {
+                    "*" => PatternSegment::AnyIndex,
+                    index => PatternSegment::Index(
+                        index
+                            .parse()
+                            .map_err(|_| invalid("Array index is not a number"))?,
+                    ),
+                });
+                rest = &rest[end + 1..];
+                if !rest.is_empty() && !rest.starts_with('[') {
+                    return Err(invalid("Unexpected characters after ]"));
+                }
+            }
+        }
+
+        Ok(PathPattern {
+            source: s.to_string(),
+            segments,
+        })
+    }
+}
+
+impl fmt::Display for PathPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(segments: &[PathSegment]) -> JsonPath {
+        JsonPath(segments.to_vec())
+    }
+
+    fn key(k: &str) -> PathSegment {
+        PathSegment::Key(k.to_string())
+    }
+
+    #[test]
+    fn test_display_path() {
+        let actual = path(&[key("items"), PathSegment::Index(2), key("id")]);
+
+        assert_eq!("items[2].id", actual.to_string());
+        assert_eq!("$", JsonPath::default().to_string());
+    }
+
+    #[test]
+    fn test_pattern_matches_exact_path() {
+        let pattern: PathPattern = "a.b".parse().unwrap();
+
+        assert!(pattern.matches(&path(&[key("a"), key("b")])));
+        assert!(!pattern.matches(&path(&[key("a")])));
+        assert!(!pattern.matches(&path(&[key("a"), key("b"), key("c")])));
+    }
+
+    #[test]
+    fn test_pattern_array_wildcards() {
+        let pattern: PathPattern = "items[*].id".parse().unwrap();
+
+        assert!(pattern.matches(&path(&[key("items"), PathSegment::Index(4), key("id")])));
+        assert!(!pattern.matches(&path(&[key("items"), key("x"), key("id")])));
+
+        let pattern: PathPattern = "items.*.id".parse().unwrap();
+        assert!(pattern.matches(&path(&[key("items"), key("x"), key("id")])));
+    }
+
+    #[test]
+    fn test_pattern_any_depth() {
+        let pattern: PathPattern = "**.traceId".parse().unwrap();
+
+        assert!(pattern.matches(&path(&[key("traceId")])));
+        assert!(pattern.matches(&path(&[key("a"), PathSegment::Index(0), key("traceId")])));
+        assert!(!pattern.matches(&path(&[key("traceId"), key("a")])));
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        assert!("".parse::<PathPattern>().is_err());
+        assert!("a..b".parse::<PathPattern>().is_err());
+        assert!("a[x]".parse::<PathPattern>().is_err());
+        assert!("a[0".parse::<PathPattern>().is_err());
+        assert!("a[0]b".parse::<PathPattern>().is_err());
+    }
+}
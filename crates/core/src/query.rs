@@ -0,0 +1,120 @@
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use serde_json::Value;
+
+use crate::error::Error;
+
+/// Compiled RFC 9535 JSONPath expression, e.g. `$.items[?@.price < 10].name`
+#[derive(Debug, Clone)]
+pub struct Query(serde_json_path::JsonPath);
+
+impl Query {
+    /// Nodes matched by the query, in document order
+    pub fn select<'a>(&self, val: &'a Value) -> Vec<&'a Value> {
+        self.0.query(val).all()
+    }
+}
+
+impl FromStr for Query {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json_path::JsonPath::parse(s)
+            .map(Query)
+            .map_err(|err| Error::InvalidQuery(err.to_string()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryFormat {
+    /// All matches as one JSON array
+    Json,
+    /// One compact JSON value per line
+    Lines,
+}
+
+impl FromStr for QueryFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(QueryFormat::Json),
+            "lines" => Ok(QueryFormat::Lines),
+            _ => Err(Error::InvalidOption(format!(
+                "Expected json or lines for query format - {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// Write query matches in `format`, prettifying the JSON array when asked
+pub fn write_matches<W: Write>(
+    out: &mut W,
+    matches: &[&Value],
+    format: QueryFormat,
+    prettify: bool,
+) -> io::Result<()> {
+    match format {
+        QueryFormat::Json if prettify => serde_json::to_writer_pretty(&mut *out, matches)?,
+        QueryFormat::Json => serde_json::to_writer(&mut *out, matches)?,
+        QueryFormat::Lines => {
+            for val in matches {
+                writeln!(out, "{}", val)?;
+            }
+            return Ok(());
+        }
+    }
+
+    writeln!(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_query_filter() {
+        let val = json!({"items": [
+            {"name": "a", "price": 5},
+            {"name": "b", "price": 15},
+            {"name": "c", "price": 1},
+        ]});
+        let query: Query = "$.items[?@.price < 10].name".parse().unwrap();
+
+        let actual = query.select(&val);
+
+        assert_eq!(vec![&json!("a"), &json!("c")], actual);
+    }
+
+    #[test]
+    fn test_query_descendants() {
+        let val = json!({"a": {"id": 1, "b": [{"id": 2}]}, "id": 0});
+        let query: Query = "$..id".parse().unwrap();
+
+        let mut actual = query.select(&val);
+        actual.sort_by_key(|val| val.as_i64());
+
+        assert_eq!(vec![&json!(0), &json!(1), &json!(2)], actual);
+    }
+
+    #[test]
+    fn test_invalid_query() {
+        let actual = "$.items[".parse::<Query>();
+
+        assert!(matches!(actual, Err(Error::InvalidQuery(_))));
+    }
+
+    #[test]
+    fn test_write_matches_lines() {
+        let (a, b) = (json!({"x": 1}), json!("y"));
+        let mut out = Vec::new();
+
+        write_matches(&mut out, &[&a, &b], QueryFormat::Lines, false).unwrap();
+
+        assert_eq!("{\"x\":1}\n\"y\"\n", String::from_utf8(out).unwrap());
+    }
+}
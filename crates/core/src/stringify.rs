@@ -1,56 +1,146 @@
-use serde_json::Value;
+use std::str::FromStr;
+
+use serde_json::{Map, Value};
 
 use crate::error::Error;
+use crate::path::JsonPath;
+
+/// What to do with a path whose key does not exist
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MissingPath {
+    /// Fail, listing every path that was not found
+    #[default]
+    Error,
+    /// Leave the document as is
+    Skip,
+    /// Create the missing keys, stringifying `null` at the end of the path
+    CreateNull,
+}
+
+impl FromStr for MissingPath {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(MissingPath::Error),
+            "skip" => Ok(MissingPath::Skip),
+            "create-null" => Ok(MissingPath::CreateNull),
+            _ => Err(Error::InvalidOption(format!(
+                "Expected error, skip or create-null for missing paths - {}",
+                s
+            ))),
+        }
+    }
+}
 
 fn sort_by_period_count(paths: Vec<&str>) -> Vec<&str> {
     let mut buf = paths;
-    buf.sort_by(|a, b| b.matches(".").count().cmp(&a.matches(".").count()));
+    buf.sort_by_key(|path| std::cmp::Reverse(path.matches(".").count()));
 
     buf
 }
 
-fn edit_val(val: &mut Value, sequence: &[&str]) -> Result<(), Error> {
-    match sequence.len() {
-        0 => Ok(()),
-        1 => {
-            if let Value::Object(obj) = val {
-                let value = obj.get(sequence[0]).unwrap();
-                obj.insert(
-                    sequence[0].to_string(),
-                    Value::String(serde_json::to_string(value).unwrap()),
-                );
-
-                Ok(())
-            } else {
-                Err(Error::JSONStringify(format!(
-                    "Value is not an object for key - {}",
-                    sequence[0]
-                )))
-            }
+/// Stringify the value at `sequence`, `depth` being the number of keys already walked.
+///
+/// Returns false when a key is missing and `missing` does not create it.
+fn edit_val(
+    val: &mut Value,
+    sequence: &[&str],
+    depth: usize,
+    missing: MissingPath,
+) -> Result<bool, Error> {
+    let key = sequence[depth];
+    let is_last = depth + 1 == sequence.len();
+
+    let Value::Object(obj) = val else {
+        return Err(Error::JSONStringify {
+            path: sequence[..=depth].join("."),
+            reason: String::from("Value is not an object"),
+        });
+    };
+
+    if !obj.contains_key(key) {
+        if missing != MissingPath::CreateNull {
+            return Ok(false);
         }
-        _ => {
-            let key = sequence[0];
-            let val_buf = val.clone();
-            let next_val = val.get_mut(key).ok_or(Error::JSONStringify(format!(
-                "Key not found - {}, for value - {:?}",
-                key, val_buf
-            )))?;
-            edit_val(next_val, &sequence[1..])
+        let created = match is_last {
+            true => Value::Null,
+            false => Value::Object(Map::new()),
+        };
+        obj.insert(key.to_string(), created);
+    }
+
+    match obj.get_mut(key) {
+        Some(next_val) if is_last => {
+            *next_val = Value::String(next_val.to_string());
+            Ok(true)
         }
+        Some(next_val) => edit_val(next_val, sequence, depth + 1, missing),
+        None => Ok(false),
     }
 }
 
 pub fn stringify(val: Value, paths: Option<Vec<&str>>) -> Result<String, Error> {
-    let sorted_paths = sort_by_period_count(paths.unwrap_or(Vec::new()));
+    stringify_with(val, paths, MissingPath::Error)
+}
+
+/// Stringify the values at (.) separated key `paths`, handling keys that do not exist by `missing`
+pub fn stringify_with(
+    val: Value,
+    paths: Option<Vec<&str>>,
+    missing: MissingPath,
+) -> Result<String, Error> {
+    let buf = stringify_paths(val, paths, missing)?;
+
+    serde_json::to_string(&buf).map_err(|err| Error::JSONStringify {
+        path: String::from("$"),
+        reason: err.to_string(),
+    })
+}
+
+/// Like [`stringify_with`] but keeps the document as a value, only turning the values at `paths`
+/// into JSON strings
+pub fn stringify_paths(
+    val: Value,
+    paths: Option<Vec<&str>>,
+    missing: MissingPath,
+) -> Result<Value, Error> {
+    let paths = paths.unwrap_or_default();
+    let sorted_paths = sort_by_period_count(paths.clone());
 
     let mut buf = val;
+    let mut not_found = Vec::new();
     for path in sorted_paths {
         let sequence: Vec<&str> = path.split('.').collect();
-        edit_val(&mut buf, sequence.as_slice())?;
+        if !edit_val(&mut buf, sequence.as_slice(), 0, missing)? {
+            not_found.push(path);
+        }
     }
 
-    Ok(serde_json::to_string(&buf)
-        .map_err(|_| Error::JSONStringify(format!("Serde stringify failed")))?)
+    if missing == MissingPath::Error && !not_found.is_empty() {
+        // Report in the order the paths were given
+        let unmatched = paths
+            .into_iter()
+            .filter(|path| not_found.contains(path))
+            .map(String::from)
+            .collect();
+        return Err(Error::MissingPaths(unmatched));
+    }
+
+    Ok(buf)
+}
+
+/// Turn the values at `paths` back into JSON strings, deepest paths first and a path once for
+/// every time it is listed, skipping paths that no longer exist
+pub fn restringify(val: &mut Value, paths: &[JsonPath]) {
+    let mut sorted: Vec<&JsonPath> = paths.iter().collect();
+    sorted.sort_by_key(|path| std::cmp::Reverse(path.0.len()));
+
+    for path in sorted {
+        if let Some(target) = path.get_mut(val) {
+            *target = Value::String(target.to_string());
+        }
+    }
 }
 
 #[cfg(test)]
@@ -87,9 +177,92 @@ mod tests {
     #[test]
     fn test_sort_paths() {
         let expected = vec!["a.b.c.d", "b.c.d", "a.c", "a"];
-        let data = vec!["a.c", "a.b.c.d", "a", "b.c.d"];
-        let actual = sort_by_period_count(data.iter().map(|x| *x).collect());
+        let data = ["a.c", "a.b.c.d", "a", "b.c.d"];
+        let actual = sort_by_period_count(data.to_vec());
 
         assert_eq!(expected, actual)
     }
+
+    #[test]
+    fn test_stringify_error_reports_path() {
+        let json = json!({"a": {"b": 1}});
+        let actual = stringify(json, Some(vec!["a.b.c"]));
+
+        match actual {
+            Err(Error::JSONStringify { path, .. }) => assert_eq!("a.b.c", path),
+            other => panic!("Expected a stringify error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_stringify_missing_paths_error_lists_all() {
+        let json = json!({"a": {"b": 1}, "c": 2});
+        let actual = stringify(json, Some(vec!["x", "a.b", "a.y.z"]));
+
+        match actual {
+            Err(Error::MissingPaths(paths)) => assert_eq!(vec!["x", "a.y.z"], paths),
+            other => panic!("Expected missing paths, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_stringify_missing_paths_skip() {
+        let json = json!({"a": {"b": 1}});
+        let expected = r#"{"a":{"b":"1"}}"#;
+
+        let actual = stringify_with(json, Some(vec!["a.b", "a.missing"]), MissingPath::Skip);
+        assert_eq!(expected, actual.unwrap());
+    }
+
+    #[test]
+    fn test_stringify_missing_paths_create_null() {
+        let json = json!({"a": 1});
+        let expected = r#"{"a":1,"b":{"c":"null"}}"#;
+
+        let actual = stringify_with(json, Some(vec!["b.c"]), MissingPath::CreateNull);
+        assert_eq!(expected, actual.unwrap());
+    }
+
+    #[test]
+    fn test_stringify_never_panics() {
+        let values = [
+            json!(null),
+            json!(1),
+            json!("a"),
+            json!([]),
+            json!([{"a": 1}]),
+            json!({}),
+            json!({"a": null}),
+            json!({"a": {"b": [1, {"c": 2}]}}),
+            json!({"": {"": 1}}),
+        ];
+        let paths = [
+            "", ".", "a", "a.", ".a", "a.b", "a.b.c", "a.b.0", "0", "..", "a.b.c.d",
+        ];
+        let policies = [
+            MissingPath::Error,
+            MissingPath::Skip,
+            MissingPath::CreateNull,
+        ];
+
+        for val in &values {
+            for policy in policies {
+                for path in paths {
+                    let _ = stringify_with(val.clone(), Some(vec![path]), policy);
+                }
+                let _ = stringify_with(val.clone(), Some(paths.to_vec()), policy);
+            }
+        }
+    }
+
+    #[test]
+    fn test_restringify() {
+        let mut json = json!({"a": {"b": [1]}, "c": 1});
+        let root = JsonPath::default();
+        let paths = vec![root.key("a"), root.key("a").key("b"), root.key("missing")];
+
+        restringify(&mut json, &paths);
+
+        assert_eq!(json!({"a": r#"{"b":"[1]"}"#, "c": 1}), json);
+    }
 }
@@ -0,0 +1,327 @@
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use serde_json::{Map, Value};
+
+use crate::error::Error;
+use crate::format::Format;
+use crate::markdown::escape_cell;
+use crate::parse::parse_document;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableFormat {
+    Csv,
+    Tsv,
+    /// GitHub-flavoured Markdown table
+    Markdown,
+}
+
+impl FromStr for TableFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(TableFormat::Csv),
+            "tsv" => Ok(TableFormat::Tsv),
+            "markdown" | "md" => Ok(TableFormat::Markdown),
+            _ => Err(Error::InvalidOption(format!(
+                "Expected csv, tsv or markdown for table format - {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// Rows of flattened values under dotted column names, e.g. `user.address.city`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// Build a table with a row per element of an array, or a single row for any other value.
+    ///
+    /// Columns appear in the order they are first seen unless `columns` selects and orders them.
+    pub fn from_value(val: &Value, columns: Option<&[String]>) -> Self {
+        let records: Vec<Map<String, Value>> = match val {
+            Value::Array(arr) => arr.iter().map(flatten).collect(),
+            val => vec![flatten(val)],
+        };
+
+        let columns = match columns {
+            Some(columns) => columns.to_vec(),
+            None => {
+                let mut columns: Vec<String> = Vec::new();
+                for record in &records {
+                    for key in record.keys() {
+                        if !columns.contains(key) {
+                            columns.push(key.clone());
+                        }
+                    }
+                }
+                columns
+            }
+        };
+
+        let rows = records
+            .iter()
+            .map(|record| {
+                columns
+                    .iter()
+                    .map(|column| record.get(column).map_or(String::new(), cell))
+                    .collect()
+            })
+            .collect();
+
+        Table { columns, rows }
+    }
+}
+
+/// Objects are flattened into dotted keys, anything else is kept whole under `value`
+fn flatten(val: &Value) -> Map<String, Value> {
+    let mut flat = Map::new();
+    match val {
+        Value::Object(obj) => flatten_into(&mut flat, "", obj),
+        val => {
+            flat.insert(String::from("value"), val.clone());
+        }
+    }
+
+    flat
+}
+
+fn flatten_into(flat: &mut Map<String, Value>, prefix: &str, obj: &Map<String, Value>) {
+    for (key, val) in obj {
+        let column = match prefix {
+            "" => key.clone(),
+            prefix => format!("{}.{}", prefix, key),
+        };
+        match val {
+            Value::Object(child) if !child.is_empty() => flatten_into(flat, &column, child),
+            val => {
+                flat.insert(column, val.clone());
+            }
+        }
+    }
+}
+
+/// Strings are written as is and null as an empty cell, everything else as JSON
+fn cell(val: &Value) -> String {
+    match val {
+        Value::String(str) => str.clone(),
+        Value::Null => String::new(),
+        val => val.to_string(),
+    }
+}
+
+fn markdown_cell(value: &str) -> String {
+    // Outside code spans backslashes and backticks are markup too
+    escape_cell(&value.replace('\\', "\\\\").replace('`', "\\`"))
+}
+
+pub fn write_table<W: Write>(out: &mut W, table: &Table, format: TableFormat) -> io::Result<()> {
+    match format {
+        TableFormat::Csv | TableFormat::Tsv => {
+            let delimiter = match format {
+                TableFormat::Tsv => b'\t',
+                _ => b',',
+            };
+            let mut writer = csv::WriterBuilder::new()
+                .delimiter(delimiter)
+                .from_writer(out);
+            writer.write_record(&table.columns)?;
+            for row in &table.rows {
+                writer.write_record(row)?;
+            }
+            writer.flush()
+        }
+        // A Markdown table needs at least one column
+        TableFormat::Markdown if table.columns.is_empty() => Ok(()),
+        TableFormat::Markdown => {
+            let line = |cells: &[String]| {
+                let cells: Vec<String> = cells.iter().map(|cell| markdown_cell(cell)).collect();
+                format!("| {} |", cells.join(" | "))
+            };
+
+            writeln!(out, "{}", line(&table.columns))?;
+            writeln!(out, "|{}", "---|".repeat(table.columns.len()))?;
+            for row in &table.rows {
+                writeln!(out, "{}", line(row))?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Read CSV with a header row into an array of objects, nesting dotted column names and
+/// unwrapping cells holding numbers or stringified JSON, empty cells becoming null
+///
+/// A dotted name is kept whole when another column names one of its parents, e.g. `a.b` next
+/// to `a`, so neither overwrites the other.
+pub fn read_csv(input: &str, format: Format) -> Result<Value, Error> {
+    let read_error = |err: csv::Error| Error::FormatRead {
+        format,
+        reason: err.to_string(),
+    };
+    let delimiter = match format {
+        Format::Tsv => b'\t',
+        _ => b',',
+    };
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .from_reader(input.as_bytes());
+    let headers = reader.headers().map_err(read_error)?.clone();
+    let whole: Vec<bool> = headers
+        .iter()
+        .map(|column| {
+            column
+                .match_indices('.')
+                .any(|(idx, _)| headers.iter().any(|other| other == &column[..idx]))
+        })
+        .collect();
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(read_error)?;
+        let mut row = Map::new();
+        for ((column, whole), field) in headers.iter().zip(&whole).zip(record.iter()) {
+            let val = match field {
+                "" => Value::Null,
+                field => Value::String(field.to_string()),
+            };
+            match whole {
+                true => {
+                    row.insert(column.to_string(), val);
+                }
+                false => insert_dotted(&mut row, column, val),
+            }
+        }
+        rows.push(Value::Object(row));
+    }
+
+    parse_document(Value::Array(rows))
+}
+
+/// Insert under a dotted column name, keeping the name whole where a parent is not an object
+fn insert_dotted(row: &mut Map<String, Value>, column: &str, val: Value) {
+    let Some((parent, rest)) = column.split_once('.') else {
+        row.insert(column.to_string(), val);
+        return;
+    };
+
+    match row
+        .entry(parent)
+        .or_insert_with(|| Value::Object(Map::new()))
+    {
+        Value::Object(child) => insert_dotted(child, rest, val),
+        _ => {
+            row.insert(column.to_string(), val);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn render(table: &Table, format: TableFormat) -> String {
+        let mut out = Vec::new();
+        write_table(&mut out, table, format).unwrap();
+
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_table_flattens_nested_keys() {
+        let val = json!([
+            {"id": 1, "user": {"name": "a", "tags": ["x"]}},
+            {"id": 2, "user": {"name": "b"}, "extra": null},
+        ]);
+
+        let actual = Table::from_value(&val, None);
+
+        let expected = Table {
+            columns: vec![
+                "id".into(),
+                "user.name".into(),
+                "user.tags".into(),
+                "extra".into(),
+            ],
+            rows: vec![
+                vec!["1".into(), "a".into(), "[\"x\"]".into(), "".into()],
+                vec!["2".into(), "b".into(), "".into(), "".into()],
+            ],
+        };
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_table_selected_columns() {
+        let val = json!([{"a": 1, "b": {"c": 2}}]);
+        let columns = vec![
+            String::from("b.c"),
+            String::from("missing"),
+            String::from("a"),
+        ];
+
+        let actual = Table::from_value(&val, Some(&columns));
+
+        assert_eq!(
+            vec![vec!["2".to_string(), "".into(), "1".into()]],
+            actual.rows
+        );
+    }
+
+    #[test]
+    fn test_write_csv_quotes_cells() {
+        let table = Table::from_value(&json!([{"a": "x,y", "b": "say \"hi\""}]), None);
+
+        assert_eq!(
+            "a,b\n\"x,y\",\"say \"\"hi\"\"\"\n",
+            render(&table, TableFormat::Csv)
+        );
+    }
+
+    #[test]
+    fn test_write_markdown_table() {
+        let table = Table::from_value(&json!([{"a": "x|y", "b": "`c`\\"}]), None);
+
+        let expected = "| a | b |\n|---|---|\n| x\\|y | \\`c\\`\\\\ |\n";
+        assert_eq!(expected, render(&table, TableFormat::Markdown));
+    }
+
+    #[test]
+    fn test_write_markdown_table_without_columns() {
+        let table = Table::from_value(&json!([]), None);
+
+        assert_eq!("", render(&table, TableFormat::Markdown));
+    }
+
+    #[test]
+    fn test_read_csv() {
+        let input = "id,user.name,meta,note\n1,a,\"{\"\"k\"\": [1]}\",\n2,b,,hello\n";
+
+        let actual = read_csv(input, Format::Csv).unwrap();
+
+        let expected = json!([
+            {"id": 1, "user": {"name": "a"}, "meta": {"k": [1]}, "note": null},
+            {"id": 2, "user": {"name": "b"}, "meta": null, "note": "hello"},
+        ]);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_read_csv_parent_column_keeps_dotted_name() {
+        let input = "a.b,a,c.d
+1,x,2
+";
+
+        let actual = read_csv(input, Format::Csv).unwrap();
+
+        assert_eq!(json!([{"a.b": 1, "a": "x", "c": {"d": 2}}]), actual);
+    }
+}
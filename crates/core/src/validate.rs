@@ -0,0 +1,175 @@
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::Error;
+
+/// Compiled JSON Schema, draft 2020-12 unless its `$schema` names an earlier draft
+pub struct Schema(jsonschema::Validator);
+
+impl Schema {
+    pub fn new(schema: &Value) -> Result<Self, Error> {
+        jsonschema::options()
+            .build(schema)
+            .map(Schema)
+            .map_err(|err| Error::InvalidSchema(format!("{} at {}", err, err.schema_path())))
+    }
+
+    /// Every constraint `instance` breaks, in the order they are found
+    pub fn validate(&self, instance: &Value) -> Vec<Violation> {
+        self.0
+            .iter_errors(instance)
+            .map(|err| Violation {
+                instance_path: err.instance_path().to_string(),
+                schema_path: err.schema_path().to_string(),
+                message: err.to_string(),
+            })
+            .collect()
+    }
+}
+
+/// Schemas are read as is, so string values that hold JSON such as `const` stay strings
+impl FromStr for Schema {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let schema: Value =
+            serde_json::from_str(s).map_err(|err| Error::InvalidSchema(err.to_string()))?;
+
+        Schema::new(&schema)
+    }
+}
+
+/// A broken constraint, both paths being JSON Pointers, e.g. `/items/0/price` and
+/// `/properties/items/items/properties/price/minimum`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Violation {
+    pub instance_path: String,
+    pub schema_path: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ViolationFormat {
+    /// One violation per line
+    #[default]
+    Text,
+    /// All violations as one JSON array
+    Json,
+}
+
+impl FromStr for ViolationFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(ViolationFormat::Text),
+            "json" => Ok(ViolationFormat::Json),
+            _ => Err(Error::InvalidOption(format!(
+                "Expected text or json for violation format - {}",
+                s
+            ))),
+        }
+    }
+}
+
+pub fn write_violations<W: Write>(
+    out: &mut W,
+    violations: &[Violation],
+    format: ViolationFormat,
+) -> io::Result<()> {
+    match format {
+        ViolationFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, violations)?;
+            writeln!(out)
+        }
+        ViolationFormat::Text => {
+            for violation in violations {
+                let instance_path = match violation.instance_path.as_str() {
+                    "" => "/",
+                    path => path,
+                };
+                writeln!(
+                    out,
+                    "{}: {} (schema {})",
+                    instance_path, violation.message, violation.schema_path
+                )?;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn schema() -> Schema {
+        Schema::new(&json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "type": "object",
+            "required": ["id"],
+            "properties": {
+                "id": {"type": "integer"},
+                "items": {"type": "array", "prefixItems": [{"type": "string"}]},
+            },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_validate_reports_paths() {
+        let actual = schema().validate(&json!({"id": "x", "items": [1]}));
+
+        let paths: Vec<(&str, &str)> = actual
+            .iter()
+            .map(|violation| {
+                (
+                    violation.instance_path.as_str(),
+                    violation.schema_path.as_str(),
+                )
+            })
+            .collect();
+        let expected = vec![
+            ("/id", "/properties/id/type"),
+            ("/items/0", "/properties/items/prefixItems/0/type"),
+        ];
+        assert_eq!(expected, paths);
+    }
+
+    #[test]
+    fn test_validate_valid_instance() {
+        assert!(
+            schema()
+                .validate(&json!({"id": 1, "items": ["a", 2]}))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_write_violations_text() {
+        let violations = schema().validate(&json!([]));
+        let mut out = Vec::new();
+
+        write_violations(&mut out, &violations, ViolationFormat::Text).unwrap();
+
+        let expected = "/: [] is not of type \"object\" (schema /type)\n";
+        assert_eq!(expected, String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn test_invalid_schema() {
+        assert!(matches!(
+            "{\"type\": 1}".parse::<Schema>(),
+            Err(Error::InvalidSchema(_))
+        ));
+        assert!(matches!(
+            "{".parse::<Schema>(),
+            Err(Error::InvalidSchema(_))
+        ));
+    }
+}
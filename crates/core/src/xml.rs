@@ -0,0 +1,378 @@
+//! XML is mapped to JSON with these conventions:
+//!
+//! - the document is an object holding the root element under its name
+//! - attributes become keys prefixed with `@`, e.g. `<a id="1">` is `{"a": {"@id": 1}}`
+//! - an element with only text becomes that text, text next to attributes or child elements
+//!   goes under `#text`, and an empty element without attributes is null
+//! - child elements sharing a name become an array in document order
+//!
+//! Writing follows the same rules in reverse, with objects and arrays given as attribute or
+//! `#text` values written as JSON text.
+
+use quick_xml::escape::partial_escape;
+use quick_xml::events::{BytesStart, BytesText, Event};
+use quick_xml::{Reader, Writer};
+use serde_json::{Map, Value};
+
+use crate::error::Error;
+use crate::format::Format;
+use crate::path::JsonPath;
+
+const TEXT_KEY: &str = "#text";
+const ATTRIBUTE_PREFIX: char = '@';
+
+struct Element {
+    name: String,
+    fields: Map<String, Value>,
+    text: String,
+}
+
+impl Element {
+    fn new(name: String) -> Self {
+        Element {
+            name,
+            fields: Map::new(),
+            text: String::new(),
+        }
+    }
+
+    fn add_child(&mut self, name: String, val: Value) {
+        match self.fields.get_mut(&name) {
+            Some(Value::Array(arr)) => arr.push(val),
+            Some(existing) => {
+                let first = existing.take();
+                *existing = Value::Array(vec![first, val]);
+            }
+            None => {
+                self.fields.insert(name, val);
+            }
+        }
+    }
+
+    fn into_value(self) -> Value {
+        if self.fields.is_empty() {
+            return match self.text.is_empty() {
+                true => Value::Null,
+                false => Value::String(self.text),
+            };
+        }
+
+        let mut fields = self.fields;
+        if !self.text.is_empty() {
+            fields.insert(TEXT_KEY.to_string(), Value::String(self.text));
+        }
+        Value::Object(fields)
+    }
+}
+
+fn element_from_start(
+    start: &BytesStart,
+    read_error: impl Fn(String) -> Error,
+) -> Result<Element, Error> {
+    let name = String::from_utf8_lossy(start.name().as_ref()).to_string();
+    let mut element = Element::new(name);
+
+    for attr in start.attributes() {
+        let attr = attr.map_err(|err| read_error(err.to_string()))?;
+        let key = String::from_utf8_lossy(attr.key.as_ref()).to_string();
+        let val = attr
+            .unescape_value()
+            .map_err(|err| read_error(err.to_string()))?;
+        element.fields.insert(
+            format!("{}{}", ATTRIBUTE_PREFIX, key),
+            Value::String(val.to_string()),
+        );
+    }
+
+    Ok(element)
+}
+
+/// Read an XML document into JSON, leaving stringified JSON in text nodes for the caller
+pub fn read_xml(input: &str) -> Result<Value, Error> {
+    let read_error = |reason: String| Error::FormatRead {
+        format: Format::Xml,
+        reason,
+    };
+
+    let mut reader = Reader::from_str(input);
+    reader.config_mut().trim_text(true);
+
+    // The bottom of the stack collects the root element
+    let mut stack = vec![Element::new(String::new())];
+    loop {
+        let event = reader.read_event().map_err(|err| {
+            read_error(format!("{} at position {}", err, reader.error_position()))
+        })?;
+
+        match event {
+            Event::Start(start) => stack.push(element_from_start(&start, read_error)?),
+            Event::Empty(start) => {
+                let element = element_from_start(&start, read_error)?;
+                let parent = stack.last_mut().expect("stack holds the document");
+                parent.add_child(element.name.clone(), element.into_value());
+            }
+            Event::Text(text) => {
+                let text = text.unescape().map_err(|err| read_error(err.to_string()))?;
+                stack.last_mut().expect("stack holds the document").text += &text;
+            }
+            Event::CData(data) => {
+                let data = data.decode().map_err(|err| read_error(err.to_string()))?;
+                stack.last_mut().expect("stack holds the document").text += &data;
+            }
+            Event::End(_) => {
+                let element = stack.pop().expect("end tags are matched by the reader");
+                let parent = stack
+                    .last_mut()
+                    .ok_or_else(|| read_error(String::from("Unexpected closing tag")))?;
+                parent.add_child(element.name.clone(), element.into_value());
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    match stack.pop() {
+        // Root elements sharing a name are collected into an array
+        Some(document)
+            if stack.is_empty()
+                && (document.fields.len() > 1 || document.fields.values().any(Value::is_array)) =>
+        {
+            Err(read_error(format!(
+                "Multiple root elements - {}",
+                document
+                    .fields
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )))
+        }
+        Some(document) if stack.is_empty() && !document.fields.is_empty() => {
+            Ok(Value::Object(document.fields))
+        }
+        Some(document) if stack.is_empty() => Err(read_error(format!(
+            "No root element{}",
+            match document.text.is_empty() {
+                true => String::new(),
+                false => format!(", found text - {}", document.text),
+            }
+        ))),
+        _ => Err(read_error(String::from("Unclosed element at end of input"))),
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|ch| ch.is_alphabetic() || ch == '_' || ch == ':')
+        && chars.all(|ch| ch.is_alphanumeric() || matches!(ch, '_' | ':' | '-' | '.'))
+}
+
+/// Text of a leaf, objects and arrays that do not map to elements are embedded as JSON
+fn text_of(val: &Value) -> String {
+    match val {
+        Value::String(str) => str.clone(),
+        val => val.to_string(),
+    }
+}
+
+/// Quotes are left as is so embedded JSON stays readable
+fn text_event(text: &str) -> BytesText<'_> {
+    BytesText::from_escaped(partial_escape(text))
+}
+
+fn write_element(
+    writer: &mut Writer<Vec<u8>>,
+    path: &JsonPath,
+    name: &str,
+    val: &Value,
+) -> Result<(), Error> {
+    let write_error = |reason: String| Error::FormatWrite {
+        format: Format::Xml,
+        path: path.clone(),
+        reason,
+    };
+    if !is_valid_name(name) {
+        return Err(write_error(format!("{} is not a valid element name", name)));
+    }
+
+    match val {
+        Value::Array(arr) => {
+            for (idx, item) in arr.iter().enumerate() {
+                if item.is_array() {
+                    return Err(Error::FormatWrite {
+                        format: Format::Xml,
+                        path: path.index(idx),
+                        reason: String::from("Nested arrays have no XML mapping"),
+                    });
+                }
+                write_element(writer, &path.index(idx), name, item)?;
+            }
+            Ok(())
+        }
+        Value::Object(obj) => {
+            let mut start = BytesStart::new(name);
+            let mut text = None;
+            let mut children = Vec::new();
+            for (key, child) in obj {
+                match key.strip_prefix(ATTRIBUTE_PREFIX) {
+                    Some(attr) if is_valid_name(attr) => {
+                        start.push_attribute((attr, text_of(child).as_str()))
+                    }
+                    Some(_) => {
+                        return Err(write_error(format!(
+                            "{} is not a valid attribute name",
+                            key
+                        )));
+                    }
+                    None if key == TEXT_KEY => text = Some(text_of(child)),
+                    None => children.push((key, child)),
+                }
+            }
+
+            if text.is_none() && children.is_empty() {
+                writer.write_event(Event::Empty(start))?;
+                return Ok(());
+            }
+            writer.write_event(Event::Start(start))?;
+            if let Some(text) = text {
+                writer.write_event(Event::Text(text_event(&text)))?;
+            }
+            for (key, child) in children {
+                write_element(writer, &path.key(key), key, child)?;
+            }
+            writer.write_event(Event::End(BytesStart::new(name).to_end()))?;
+            Ok(())
+        }
+        Value::Null => {
+            writer.write_event(Event::Empty(BytesStart::new(name)))?;
+            Ok(())
+        }
+        val => {
+            writer.write_event(Event::Start(BytesStart::new(name)))?;
+            writer.write_event(Event::Text(text_event(&text_of(val))))?;
+            writer.write_event(Event::End(BytesStart::new(name).to_end()))?;
+            Ok(())
+        }
+    }
+}
+
+/// Write JSON as XML, the value must be an object with a single key naming the root element
+pub fn write_xml(val: &Value, pretty: bool) -> Result<String, Error> {
+    let root = JsonPath::default();
+    let (name, element) = match val {
+        Value::Object(obj) if obj.len() == 1 => obj.iter().next().expect("object has one key"),
+        _ => {
+            return Err(Error::FormatWrite {
+                format: Format::Xml,
+                path: root,
+                reason: String::from(
+                    "XML needs a single root element, wrap it in an object with one key",
+                ),
+            });
+        }
+    };
+    if element.is_array() {
+        return Err(Error::FormatWrite {
+            format: Format::Xml,
+            path: root.key(name),
+            reason: String::from("XML needs a single root element, found an array"),
+        });
+    }
+
+    let mut writer = match pretty {
+        true => Writer::new_with_indent(Vec::new(), b' ', 2),
+        false => Writer::new(Vec::new()),
+    };
+    write_element(&mut writer, &root.key(name), name, element)?;
+
+    let mut out = String::from_utf8_lossy(&writer.into_inner()).to_string();
+    out.push('\n');
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_read_xml_mapping() {
+        let input = r#"<?xml version="1.0"?>
+            <order id="7">
+                <item sku="a">Pen</item>
+                <item sku="b">Ink &amp; nib</item>
+                <note/>
+                <total currency="EUR">12.5</total>
+            </order>"#;
+
+        let actual = read_xml(input).unwrap();
+
+        let expected = json!({"order": {
+            "@id": "7",
+            "item": [
+                {"@sku": "a", "#text": "Pen"},
+                {"@sku": "b", "#text": "Ink & nib"},
+            ],
+            "note": null,
+            "total": {"@currency": "EUR", "#text": "12.5"},
+        }});
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_read_xml_cdata_and_errors() {
+        let actual = read_xml("<a><![CDATA[{\"b\": 1}]]></a>").unwrap();
+        assert_eq!(json!({"a": "{\"b\": 1}"}), actual);
+
+        assert!(matches!(
+            read_xml("<a><b></a>"),
+            Err(Error::FormatRead { .. })
+        ));
+        assert!(matches!(read_xml("<a>"), Err(Error::FormatRead { .. })));
+        assert!(matches!(read_xml("text"), Err(Error::FormatRead { .. })));
+        assert!(matches!(
+            read_xml("<a/><b/>"),
+            Err(Error::FormatRead { .. })
+        ));
+        assert!(matches!(
+            read_xml("<a>1</a><a>2</a>"),
+            Err(Error::FormatRead { .. })
+        ));
+    }
+
+    #[test]
+    fn test_write_xml_round_trip() {
+        let val = json!({"order": {
+            "@id": "7",
+            "item": [{"@sku": "a", "#text": "Pen"}, "Ink & nib"],
+            "meta": {"json": {"x": [1]}},
+            "note": null,
+        }});
+
+        let actual = write_xml(&val, false).unwrap();
+
+        let expected = "<order id=\"7\"><item sku=\"a\">Pen</item><item>Ink &amp; nib</item>\
+            <meta><json><x>1</x></json></meta><note/></order>\n";
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_write_xml_errors() {
+        assert!(matches!(
+            write_xml(&json!({"a": 1, "b": 2}), false),
+            Err(Error::FormatWrite { .. })
+        ));
+        match write_xml(&json!({"a": {"bad key": 1}}), false) {
+            Err(Error::FormatWrite { path, .. }) => assert_eq!("a.bad key", path.to_string()),
+            other => panic!("Expected an XML write error, got {:?}", other),
+        }
+        match write_xml(&json!({"r": {"x": [[1, 2], [3]]}}), false) {
+            Err(Error::FormatWrite { path, .. }) => assert_eq!("r.x[0]", path.to_string()),
+            other => panic!("Expected an XML write error, got {:?}", other),
+        }
+    }
+}